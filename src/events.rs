@@ -1,9 +1,50 @@
-use std::{borrow::Borrow, collections::HashSet, hash::Hash, sync::mpsc::{channel, Receiver}, thread, time::{Duration, Instant}};
+use std::{collections::HashSet, hash::Hash, sync::mpsc::{channel, Receiver}, thread, time::{Duration, Instant}};
 
 pub use rdev::Key;
 pub use std::time::SystemTime as Time;
 
-use crate::{error, BooleanConditional};
+use crate::error;
+
+
+const KEYS: &[Key] = &[
+    Key::Alt, Key::AltGr, Key::Backspace, Key::CapsLock, Key::ControlLeft, Key::ControlRight, Key::Delete, Key::DownArrow,
+    Key::End, Key::Escape, Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10,
+    Key::F11, Key::F12, Key::Home, Key::LeftArrow, Key::MetaLeft, Key::MetaRight, Key::PageDown, Key::PageUp, Key::Return,
+    Key::RightArrow, Key::ShiftLeft, Key::ShiftRight, Key::Space, Key::Tab, Key::UpArrow, Key::PrintScreen, Key::ScrollLock,
+    Key::Pause, Key::NumLock, Key::BackQuote, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7,
+    Key::Num8, Key::Num9, Key::Num0, Key::Minus, Key::Equal, Key::KeyQ, Key::KeyW, Key::KeyE, Key::KeyR, Key::KeyT,
+    Key::KeyY, Key::KeyU, Key::KeyI, Key::KeyO, Key::KeyP, Key::LeftBracket, Key::RightBracket, Key::KeyA, Key::KeyS,
+    Key::KeyD, Key::KeyF, Key::KeyG, Key::KeyH, Key::KeyJ, Key::KeyK, Key::KeyL, Key::SemiColon, Key::Quote, Key::BackSlash,
+    Key::IntlBackslash, Key::KeyZ, Key::KeyX, Key::KeyC, Key::KeyV, Key::KeyB, Key::KeyN, Key::KeyM, Key::Comma, Key::Dot,
+    Key::Slash, Key::Insert, Key::KpReturn, Key::KpMinus, Key::KpPlus, Key::KpMultiply, Key::KpDivide, Key::Kp0, Key::Kp1,
+    Key::Kp2, Key::Kp3, Key::Kp4, Key::Kp5, Key::Kp6, Key::Kp7, Key::Kp8, Key::Kp9, Key::KpDelete, Key::Function,
+];
+
+
+// Accepts the names of rdev keys (e.g. "KeyC", "UpArrow"), single letters and digits, and common aliases, ignoring case
+pub fn parse_key(name: &str) -> Option<Key> {
+    let name = name.trim().to_ascii_lowercase();
+    
+    let key = match name.as_str() {
+        "shift" => Key::ShiftLeft,
+        "ctrl" | "control" => Key::ControlLeft,
+        "meta" | "super" | "win" => Key::MetaLeft,
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        "enter" => Key::Return,
+        "esc" => Key::Escape,
+        "del" => Key::Delete,
+        "pgup" => Key::PageUp,
+        "pgdown" => Key::PageDown,
+        _ if name.len() == 1 && name.as_bytes()[0].is_ascii_alphabetic() => return parse_key(&format!("Key{}", name)),
+        _ if name.len() == 1 && name.as_bytes()[0].is_ascii_digit() => return parse_key(&format!("Num{}", name)),
+        _ => return KEYS.iter().find(|key| format!("{:?}", key).to_ascii_lowercase() == name).copied(),
+    };
+    
+    Some(key)
+}
 
 
 #[derive(PartialEq, Eq)]
@@ -24,6 +65,11 @@ impl KeyCombination {
     pub fn remove(&mut self, key: Key) {
         self.0.remove(&key);
     }
+    
+    #[inline(always)]
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.0.is_subset(&other.0)
+    }
 }
 
 impl FromIterator<Key> for KeyCombination {
@@ -39,10 +85,163 @@ pub enum Event {
     Released(Key, Time),
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Gesture {
+    Press, // fires as soon as the keys are down, repeating while held
+    Tap, // fires when the keys are released before a long press
+    Hold, // fires once when the keys are held for a long press
+    DoubleTap, // fires when the keys are pressed twice in a short time
+}
+
+#[derive(Clone)]
+pub struct Binding {
+    pub gesture: Gesture,
+    pub steps: Vec<Vec<Key>>,
+}
+
+// A combination of keys held down at the same time, or a sequence of them pressed one after another
+struct Combination {
+    steps: Vec<KeyCombination>,
+    gesture: Gesture,
+    duration: Duration,
+    interval: Duration,
+    acceleration: f64,
+    last: Option<Instant>,
+    since: Option<Instant>,
+    released: Option<Instant>,
+    fired: bool,
+    waits_double_tap: bool,
+    progress: usize,
+    deadline: Option<Instant>,
+    pressed: bool,
+}
+
 pub struct EventListener {
     receiver: Receiver<rdev::Event>,
     keys: KeyCombination,
-    combinations: Vec<(KeyCombination, Duration, Option<Instant>, bool)>,
+    combinations: Vec<Combination>,
+    sequence_timeout: Duration,
+    long_press: Duration,
+    double_tap: Duration,
+}
+
+impl Combination {
+    #[inline(always)]
+    fn is_sequence(&self) -> bool {
+        self.steps.len() > 1
+    }
+    
+    #[inline(always)]
+    fn advance(&mut self, keys: &KeyCombination, now: Instant, timeout: Duration) {
+        if self.deadline.is_some_and(|deadline| now > deadline) {
+            self.progress = 0;
+        }
+        
+        if keys == &self.steps[self.progress] {
+            self.progress += 1;
+        }
+        else if keys == &self.steps[0] {
+            self.progress = 1;
+        }
+        else if !keys.is_subset(&self.steps[self.progress]) {
+            self.progress = 0;
+        }
+        
+        if self.progress == self.steps.len() {
+            self.pressed = true;
+            self.progress = 0;
+        }
+        
+        self.deadline = (self.progress > 0).then(|| now + timeout);
+    }
+    
+    #[inline(always)]
+    fn press(&mut self, active: bool, now: Instant) {
+        let overtime = !self.last.is_some_and(|t| now - t < self.interval);
+        
+        self.pressed = active && overtime;
+        
+        if !active {
+            self.last = None;
+            self.interval = self.duration;
+        }
+        else if overtime {
+            // the longer the keys are held, the faster it repeats
+            if self.last.is_some() {
+                self.interval = self.interval.mul_f64(self.acceleration).max(self.duration / 4);
+            }
+            
+            self.last.replace(now);
+        }
+    }
+    
+    #[inline(always)]
+    fn tap(&mut self, active: bool, now: Instant, long_press: Duration, double_tap: Duration) {
+        self.pressed = false;
+        
+        if active {
+            if self.since.is_none() && self.released.take().is_some() {
+                self.fired = true; // a double tap is coming instead
+            }
+            
+            self.since.get_or_insert(now);
+        }
+        else if let Some(since) = self.since.take() {
+            if !std::mem::take(&mut self.fired) && now - since < long_press {
+                if self.waits_double_tap {
+                    self.released.replace(now);
+                }
+                else {
+                    self.pressed = true;
+                }
+            }
+        }
+        
+        if self.released.is_some_and(|t| now - t > double_tap) {
+            self.released = None;
+            self.pressed = true;
+        }
+    }
+    
+    #[inline(always)]
+    fn hold(&mut self, active: bool, now: Instant, long_press: Duration) {
+        self.pressed = false;
+        
+        if active {
+            let since = *self.since.get_or_insert(now);
+            
+            if !self.fired && now - since >= long_press {
+                self.pressed = true;
+                self.fired = true;
+            }
+        }
+        else {
+            self.since = None;
+            self.fired = false;
+        }
+    }
+    
+    #[inline(always)]
+    fn double_tap(&mut self, active: bool, now: Instant, double_tap: Duration) {
+        self.pressed = false;
+        
+        if active {
+            if self.since.is_none() {
+                self.since.replace(now);
+                
+                if self.released.take().is_some_and(|t| now - t <= double_tap) {
+                    self.pressed = true;
+                    self.fired = true;
+                }
+            }
+        }
+        else if self.since.take().is_some() {
+            // the second tap does not start another double tap
+            if !std::mem::take(&mut self.fired) {
+                self.released.replace(now);
+            }
+        }
+    }
 }
 
 impl Event {
@@ -112,6 +311,9 @@ impl EventListener {
             receiver: r,
             keys: KeyCombination::new(),
             combinations: Vec::new(),
+            sequence_timeout: Duration::from_secs(1),
+            long_press: Duration::from_millis(500),
+            double_tap: Duration::from_millis(300),
         }
     }
     
@@ -119,39 +321,97 @@ impl EventListener {
     pub fn poll_events(&mut self) {
         let now = Instant::now();
         
+        for comb in self.combinations.iter_mut().filter(|comb| comb.is_sequence()) {
+            comb.pressed = false;
+        }
+        
         for e in self.receiver.try_iter().filter_map(|e| Event::try_from(e).ok()) {
             if e.released() {
                 self.keys.remove(e.key());
             }
             else {
                 self.keys.add(e.key());
+                
+                for comb in self.combinations.iter_mut().filter(|comb| comb.is_sequence()) {
+                    comb.advance(&self.keys, now, self.sequence_timeout);
+                }
             }
         }
         
-        for (comb, duration, t, flag) in &mut self.combinations {
-            let overtime = !t.is_some_and(|t| now - t < *duration);
+        for comb in self.combinations.iter_mut().filter(|comb| !comb.is_sequence()) {
+            let active = self.keys == comb.steps[0];
             
-            *flag = (&self.keys == comb).elsedo(|| { t.take(); }) && overtime.ifdo(|| { t.replace(now); });
+            match comb.gesture {
+                Gesture::Press => comb.press(active, now),
+                Gesture::Tap => comb.tap(active, now, self.long_press, self.double_tap),
+                Gesture::Hold => comb.hold(active, now, self.long_press),
+                Gesture::DoubleTap => comb.double_tap(active, now, self.double_tap),
+            }
         }
     }
     
+    // A sequence fires once when its last step is pressed, only a single combination has gestures and repeats every duration
     #[inline(always)]
-    pub fn register_combination<T: Borrow<Key>>(&mut self, keys: impl IntoIterator<Item = T>, duration: Duration) -> usize {
+    pub fn register(&mut self, binding: &Binding, duration: Duration) -> usize {
         let id = self.combinations.len();
+        let steps: Vec<KeyCombination> = binding.steps.iter().map(|keys| keys.iter().copied().collect()).collect();
+        
+        // a tap has to wait and see if it is a double tap when both are bound to the same keys
+        let mut waits_double_tap = false;
+        
+        for comb in self.combinations.iter_mut().filter(|comb| comb.steps == steps) {
+            match (binding.gesture, comb.gesture) {
+                (Gesture::DoubleTap, Gesture::Tap) => comb.waits_double_tap = true,
+                (Gesture::Tap, Gesture::DoubleTap) => waits_double_tap = true,
+                _ => {}
+            }
+        }
         
-        self.combinations.push((keys.into_iter().map(|t| *t.borrow()).collect(), duration, None, false));
+        self.combinations.push(Combination {
+            steps,
+            gesture: binding.gesture,
+            duration,
+            interval: duration,
+            acceleration: 1.,
+            last: None,
+            since: None,
+            released: None,
+            fired: false,
+            waits_double_tap,
+            progress: 0,
+            deadline: None,
+            pressed: false,
+        });
         
         id
     }
     
     #[inline(always)]
-    pub fn register_once_combination<T: Borrow<Key>>(&mut self, keys: impl IntoIterator<Item = T>) -> usize {
-        self.register_combination(keys, Duration::MAX)
+    pub fn set_duration(&mut self, combination: usize, duration: Duration) {
+        self.combinations[combination].duration = duration;
+        self.combinations[combination].interval = duration;
+    }
+    
+    // How much the interval between repeats is multiplied by every repeat, 1 for no acceleration
+    #[inline(always)]
+    pub fn set_acceleration(&mut self, combination: usize, acceleration: f64) {
+        self.combinations[combination].acceleration = acceleration;
+    }
+    
+    #[inline(always)]
+    pub fn set_gesture_durations(&mut self, long_press: Duration, double_tap: Duration) {
+        self.long_press = long_press;
+        self.double_tap = double_tap;
+    }
+    
+    #[inline(always)]
+    pub fn set_sequence_timeout(&mut self, timeout: Duration) {
+        self.sequence_timeout = timeout;
     }
     
     #[inline(always)]
     pub fn is_pressed(&self, combination: usize) -> bool {
-        self.combinations[combination].3
+        self.combinations[combination].pressed
     }
     
     #[inline(always)]
@@ -0,0 +1,131 @@
+use std::{fs::{create_dir_all, read_dir, write}, path::{Path, PathBuf}, time::Duration};
+
+use crate::{error, lrc::{format_timestamp, Lrc}, lyrics::Line, metadata::Song};
+
+
+// How long the last line stays on screen in subtitles
+const LAST_LINE_DURATION: Duration = Duration::from_secs(5);
+
+
+#[derive(Clone, Copy)]
+pub enum LyricsFormat {
+    Lrc,
+    Srt,
+    Text,
+}
+
+
+#[inline(always)]
+fn format_srt_timestamp(time: Duration) -> String {
+    let ms = time.as_millis();
+    
+    format!("{:02}:{:02}:{:02},{:03}", ms / 3600000, ms / 60000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+
+impl LyricsFormat {
+    #[inline(always)]
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "lrc" => Some(LyricsFormat::Lrc),
+            "srt" => Some(LyricsFormat::Srt),
+            "txt" | "text" => Some(LyricsFormat::Text),
+            _ => None,
+        }
+    }
+    
+    #[inline(always)]
+    pub fn extension(self) -> &'static str {
+        match self {
+            LyricsFormat::Lrc => "lrc",
+            LyricsFormat::Srt => "srt",
+            LyricsFormat::Text => "txt",
+        }
+    }
+    
+    // Lines with word timing are written as Enhanced LRC
+    pub fn export(self, lyrics: Vec<(Duration, String)>) -> String {
+        let lines = Line::group(lyrics);
+        
+        match self {
+            LyricsFormat::Lrc => Lrc {
+                lines: lines.iter().map(|line| {
+                    let text = if line.words.len() > 1 {
+                        line.words.iter().enumerate().map(|(i, &(time, start))| {
+                            let end = line.words.get(i + 1).map_or(line.text.len(), |&(_, end)| end);
+                            
+                            format!("<{}>{}", format_timestamp(time), &line.text[start..end])
+                        }).collect()
+                    }
+                    else {
+                        line.text.clone()
+                    };
+                    
+                    (line.start(), text)
+                }).collect(),
+                ..Default::default()
+            }.to_string(),
+            LyricsFormat::Srt => lines.iter().enumerate().map(|(i, line)| {
+                let end = lines.get(i + 1).map_or(line.start() + LAST_LINE_DURATION, Line::start);
+                
+                format!("{}\n{} --> {}\n{}\n\n", i + 1, format_srt_timestamp(line.start()), format_srt_timestamp(end), line.text)
+            }).collect(),
+            LyricsFormat::Text => lines.iter().map(|line| format!("{}\n", line.text)).collect(),
+        }
+    }
+    
+    // Exports the lyrics of a song, or of every song in a directory, to the output file or directory,
+    // or next to the songs without one, returns how many songs were exported.
+    // Existing files are skipped unless forced, e.g. a hand-made .lrc next to a song is also its lyrics
+    pub fn export_path(self, input: &Path, output: Option<&Path>, languages: &[String], force: bool) -> usize {
+        if input.is_dir() {
+            let entries = match read_dir(input) {
+                Ok(entries) => entries,
+                Err(e) => { error!(e, "Failed to read directory {}", input.display()); return 0; }
+            };
+            
+            if let Some(output) = output {
+                if let Err(e) = create_dir_all(output) {
+                    error!(e, "Failed to create directory {}", output.display());
+                    return 0;
+                }
+            }
+            
+            let mut paths: Vec<PathBuf> = entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_file()).collect();
+            
+            paths.sort();
+            
+            paths.iter().filter(|path| {
+                // files that are not songs or have no synced lyrics are skipped quietly
+                let Ok(Some(lyrics)) = Song::synced_lyrics(path, languages) else { return false };
+                
+                let target = match (output, path.file_name()) {
+                    (Some(dir), Some(name)) => dir.join(name),
+                    _ => path.to_path_buf(),
+                };
+                
+                self.write(&target.with_extension(self.extension()), lyrics, force)
+            }).count()
+        }
+        else {
+            match Song::synced_lyrics(input, languages) {
+                Ok(Some(lyrics)) => self.write(&output.map_or_else(|| input.with_extension(self.extension()), Path::to_path_buf), lyrics, force) as usize,
+                Ok(None) => { error!("{} has no synced lyrics", input.display()); 0 }
+                Err(e) => { error!(e, "Failed to read tag from {}", input.display()); 0 }
+            }
+        }
+    }
+    
+    #[inline(always)]
+    fn write(self, path: &Path, lyrics: Vec<(Duration, String)>, force: bool) -> bool {
+        if !force && path.exists() {
+            error!("Skipped {} which already exists", path.display());
+            return false;
+        }
+        
+        match write(path, self.export(lyrics)) {
+            Ok(()) => true,
+            Err(e) => { error!(e, "Failed to write {}", path.display()); false }
+        }
+    }
+}
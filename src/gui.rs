@@ -58,6 +58,13 @@ enum_str!(GUICommand
     FAST_FORWARD(Duration)
     REWIND(Duration)
     PLAY(String)
+    SEEK(Duration)
+    LYRICS(Vec<(Option<Duration>, String)>)
+    LYRICS_LINE(Option<usize>)
+    LYRICS_ESTIMATED(bool)
+    UPCOMING(Vec<String>)
+    HISTORY(Vec<String>, Option<usize>)
+    PLAYLIST(String, Vec<String>)
     EXIT
 );
 
@@ -75,6 +82,13 @@ impl GUICommand {
             FAST_FORWARD(duration) => args!(self, duration.as_secs_f64().to_string()),
             REWIND(duration) => args!(self, duration.as_secs_f64().to_string()),
             PLAY(song) => args!(self, song),
+            LYRICS_LINE(index) => args!(self, to_string(index).unwrap()),
+            LYRICS_ESTIMATED(estimated) => args!(self, estimated.to_string()),
+            SEEK(position) => args!(self, position.as_secs_f64().to_string()),
+            LYRICS(lines) => args!(self, to_string(&lines.iter().map(|(time, s)| (time.map(|time| time.as_secs_f64()), s)).collect::<Vec<_>>()).unwrap()),
+            UPCOMING(songs) => args!(self, to_string(songs).unwrap()),
+            PLAYLIST(id, songs) => args!(self, id, to_string(songs).unwrap()),
+            HISTORY(songs, cursor) => args!(self, to_string(cursor).unwrap(), to_string(songs).unwrap()),
             _ => self.stringify(),
         };
         
@@ -4,6 +4,7 @@ extends Tree
 
 var scene_root: Root
 var root: TreeItem
+var id := "%08x" % randi() # tells the backend which playlist it is playing
 var count: int:
 	get: return root.get_child_count()
 var first: TreeItem:
@@ -129,7 +130,30 @@ func add_song(song: Song) -> void:
 		prints("APPEND", song.path)
 
 
+func replace_songs(paths: Array, songs: Dictionary) -> void:
+	for item in root.get_children():
+		root.remove_child(item)
+		item.free()
+	
+	for path in paths:
+		var item := create_item()
+		
+		if songs.has(path):
+			var song := songs[path] as Song
+			
+			item.set_text(0, song.title)
+			item.set_text(1, song.artist)
+			item.set_text(2, song.album)
+		else:
+			item.set_text(0, path.get_file())
+		
+		item.set_text(3, path)
+
+
 func validate(songs: Dictionary) -> void:
+	if is_playing:
+		print("BEGIN_EDIT")
+	
 	for i in range(count - 1, -1, -1):
 		var item := root.get_child(i)
 		
@@ -145,6 +169,9 @@ func validate(songs: Dictionary) -> void:
 			
 			if is_playing:
 				prints("DELETE", i)
+	
+	if is_playing:
+		print("END_EDIT")
 
 
 func find_song(songs: Dictionary, i: int, item: TreeItem) -> bool:
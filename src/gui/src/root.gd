@@ -32,7 +32,11 @@ var opening_playlist_path: String
 var playing_playlist: Playlist:
 	set(value):
 		if playing_playlist != value:
-			print("DELETE_ALL")
+			# the backend already plays the playlist when it is restored by undo or redo
+			if not restoring_playlist:
+				print("BEGIN_EDIT")
+				prints("PLAYING", value.id if value else "")
+				print("DELETE_ALL")
 			
 			if playing_playlist:
 				playlists.set_tab_icon(playlists.get_children().find(playing_playlist), null)
@@ -40,10 +44,15 @@ var playing_playlist: Playlist:
 			if value:
 				playlists.set_tab_icon(playlists.get_children().find(value), preload("res://src/play.svg"))
 				
-				for item in value.root.get_children():
-					prints("APPEND", item.get_text(3))
+				if not restoring_playlist:
+					for item in value.root.get_children():
+						prints("APPEND", item.get_text(3))
+			
+			if not restoring_playlist:
+				print("END_EDIT")
 			
 			playing_playlist = value
+var restoring_playlist := false
 var current_playlist: Playlist:
 	get: return playlists.get_current_tab_control() as Playlist
 
@@ -272,6 +281,12 @@ func read_cache() -> bool:
 				for i in range(pn.size()):
 					playlists.set_tab_title(i, pn[i])
 				
+				if json.has(&"playlists_ids"):
+					var ids := json.playlists_ids as Array
+					
+					for i in range(ids.size()):
+						(playlists.get_child(i) as Playlist).id = ids[i]
+				
 				playlists.current_tab = json.focused_playlist
 				playing_playlist = playlists.get_child(json.playing_playlist)
 				
@@ -293,6 +308,7 @@ func save_cache() -> void:
 		playing_playlist = playlists.get_children().find(playing_playlist),
 		playlists = serialize_playlists(),
 		playlists_names = playlists_names(),
+		playlists_ids = playlists_ids(),
 	}
 	
 	if theme != default_theme:
@@ -319,6 +335,15 @@ func serialize_playlists() -> Array[Array]:
 	return array
 
 
+func playlists_ids() -> Array[String]:
+	var array: Array[String] = []
+	
+	for playlist in playlists.get_children():
+		array.append(playlist.id)
+	
+	return array
+
+
 func playlists_names() -> Array[String]:
 	var array: Array[String] = []
 	
@@ -575,8 +600,29 @@ func command(string: String) -> void:
 		"FAST_FORWARD":
 			song_position += float(string.substr(c.length() + 1))
 		
+		"SEEK":
+			song_position = float(string.substr(c.length() + 1))
+		
 		"REPLAY":
 			song_position = 0
+		
+		"PLAYLIST":
+			var args := string.substr(c.length() + 1)
+			var i := args.find(" ")
+			var id := args.left(i)
+			var playlist: Playlist = null
+			
+			for p in playlists.get_children():
+				if p.id == id:
+					playlist = p
+			
+			# switches back to the playlist the restored songs come from
+			restoring_playlist = true
+			playing_playlist = playlist
+			restoring_playlist = false
+			
+			if playlist:
+				playlist.replace_songs(JSON.parse_string(args.substr(i + 1)), songs)
 
 
 func show_song(song: Song) -> void:
@@ -621,7 +667,10 @@ func item_pressed(item: TreeItem, silent: bool):
 
 
 func _on_library_item_activated() -> void:
+	# an album or an artist is undone as a whole
+	print("BEGIN_EDIT")
 	item_pressed(library.get_selected(), Input.is_action_pressed(&"silent_add"))
+	print("END_EDIT")
 
 
 func _on_library_item_selected() -> void:
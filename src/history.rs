@@ -1,12 +1,14 @@
-use std::mem::MaybeUninit;
+use std::collections::VecDeque;
 
 use serde::{Serialize, Deserialize};
 
 
+pub const DEFAULT_HISTORY_CAPACITY: usize = 100;
+
+
+#[derive(Clone)]
 pub struct History<T> {
-    vec: Vec<MaybeUninit<T>>,
-    len: usize,
-    offset: usize,
+    vec: VecDeque<T>,
     lookback: usize,
     capacity: usize,
 }
@@ -16,71 +18,50 @@ pub struct History<T> {
 struct SerializedHistory<T> {
     vec: Vec<T>,
     lookback: usize,
+    #[serde(default)]
+    capacity: Option<usize>,
 }
 
 
 impl<T: Eq> History<T> {
+    // The capacity is at least 1 so that the current entry is always kept
     #[inline(always)]
     pub fn new(capacity: usize) -> Self {
         Self {
-            vec: (0..capacity).map(|_| MaybeUninit::uninit()).collect(),
-            len: 0,
-            offset: 0,
+            vec: VecDeque::new(),
             lookback: 0,
-            capacity,
+            capacity: capacity.max(1),
         }
     }
     
-    // Must only be called before any push
+    // Drops the oldest entries if there are more than the new capacity, which is at least 1
     #[inline(always)]
-    pub unsafe fn set_capacity(&mut self, capacity: usize) {
-        if capacity > self.capacity {
-            self.vec.extend((self.capacity..capacity).map(|_| MaybeUninit::uninit()));
-        }
-        else if capacity < self.capacity {
-            self.vec.drain(0..(self.capacity - capacity));
+    pub fn set_capacity(&mut self, capacity: usize) {
+        let capacity = capacity.max(1);
+        
+        if self.vec.len() > capacity {
+            self.vec.drain(..self.vec.len() - capacity);
+            self.lookback = self.lookback.min(self.vec.len().saturating_sub(1));
         }
         
         self.capacity = capacity;
     }
     
-    #[inline(always)]
-    fn get_offset_index(&self, i: usize) -> usize {
-        let i = i + self.offset;
-        
-        if i >= self.capacity {
-            i - self.capacity
-        }
-        else {
-            i
-        }
-    }
-    
     #[inline(always)]
     pub fn get(&self, i: usize) -> Option<&T> {
-        (i < self.len).then(|| self.get_unchecked(i))
-    }
-    
-    #[inline(always)]
-    pub fn get_unchecked(&self, i: usize) -> &T {
-        unsafe { self.vec[self.get_offset_index(i)].assume_init_ref() }
+        self.vec.get(i)
     }
     
     #[inline(always)]
     pub fn push(&mut self, t: T) {
-        self.len -= self.lookback;
+        self.vec.truncate(self.vec.len() - self.lookback);
         self.lookback = 0;
         
         if !self.get_latest().is_some_and(|u| u == &t) {
-            let i = self.get_offset_index(self.len);
-            
-            self.vec[i].write(t);
+            self.vec.push_back(t);
             
-            if self.len < self.capacity {
-                self.len += 1;
-            }
-            else {
-                self.offset += 1;
+            if self.vec.len() > self.capacity {
+                self.vec.pop_front();
             }
         }
     }
@@ -89,7 +70,7 @@ impl<T: Eq> History<T> {
     pub fn look_back(&mut self) -> Option<&T> {
         if self.can_look_back() {
             self.lookback += 1;
-            self.get(self.len - 1 - self.lookback)
+            self.get_current()
         }
         else {
             None
@@ -108,18 +89,41 @@ impl<T: Eq> History<T> {
         }
     }
     
+    #[inline(always)]
+    pub fn jump(&mut self, i: usize) -> Option<&T> {
+        if i < self.vec.len() {
+            self.lookback = self.vec.len() - 1 - i;
+            
+            self.get_current()
+        }
+        else {
+            None
+        }
+    }
+    
+    #[inline(always)]
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.vec.iter()
+    }
+    
+    // index of the current entry, counted from the oldest
+    #[inline(always)]
+    pub fn cursor(&self) -> Option<usize> {
+        (!self.vec.is_empty()).then(|| self.vec.len() - 1 - self.lookback)
+    }
+    
     pub fn can_look_back(&self) -> bool {
-        self.lookback + 1 < self.len
+        self.lookback + 1 < self.vec.len()
     }
     
     #[inline(always)]
     pub fn get_current(&self) -> Option<&T> {
-        self.get(self.len - 1 - self.lookback)
+        self.cursor().and_then(|i| self.get(i))
     }
     
     #[inline(always)]
     pub fn get_latest(&self) -> Option<&T> {
-        (self.len > 0).then(|| self.get_unchecked(self.len - 1))
+        self.vec.back()
     }
     
     #[inline(always)]
@@ -129,14 +133,15 @@ impl<T: Eq> History<T> {
 }
 
 
-impl<T: Clone + Serialize + Eq> Serialize for History<T> {
+impl<T: Clone + Serialize> Serialize for History<T> {
     #[inline(always)]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
             S: serde::Serializer {
         let s = SerializedHistory {
-            vec: (0..self.len).map(|i| self.get_unchecked(i).clone()).collect(),
+            vec: self.vec.iter().cloned().collect(),
             lookback: self.lookback,
+            capacity: Some(self.capacity),
         };
         
         s.serialize(serializer)
@@ -149,15 +154,18 @@ impl<'de, T: Deserialize<'de>> Deserialize<'de> for History<T> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         where
             D: serde::Deserializer<'de> {
-        let SerializedHistory { vec, lookback } = SerializedHistory::deserialize(deserializer)?;
-        let len = vec.len();
+        let SerializedHistory { vec, lookback, capacity } = SerializedHistory::deserialize(deserializer)?;
+        let capacity = capacity.filter(|&c| c > 0).unwrap_or(DEFAULT_HISTORY_CAPACITY);
+        let mut vec = VecDeque::from(vec);
+        
+        if vec.len() > capacity {
+            vec.drain(..vec.len() - capacity);
+        }
         
         Ok(History {
-            vec: vec.into_iter().map(MaybeUninit::new).collect(),
-            len,
-            offset: 0,
-            lookback,
-            capacity: len,
+            lookback: lookback.min(vec.len().saturating_sub(1)),
+            vec,
+            capacity,
         })
     }
 }
@@ -0,0 +1,302 @@
+use std::{collections::{BTreeMap, HashMap, HashSet}, fmt::Display, fs::{read_to_string, write}, io::ErrorKind, path::Path};
+
+use serde_json::{from_str, to_string_pretty};
+
+use crate::events::{parse_key, Binding, Gesture, Key};
+
+
+// Not an action, "Leader" in a binding is replaced by the binding of the leader
+pub const LEADER: &str = "leader";
+
+pub const DEFAULT_KEYBINDINGS: &[(&str, &str)] = &[
+    ("toggle_gui", "Alt+C"),
+    ("quit_app", "Alt+E"),
+    ("reload_settings", "Alt+Shift+S"),
+    ("pause_resume_song", "Alt+Space"),
+    ("stop_player", "Alt+Shift+Space"),
+    ("toggle_repeat_mode", "Alt+R"),
+    ("toggle_shuffling", "Alt+Shift+R"),
+    ("toggle_mute", "Alt+M"),
+    ("toggle_stop_next", "Alt+Shift+M"),
+    ("jump_to_begin", "Alt+Ctrl+Left"),
+    ("jump_to_end", "Alt+Ctrl+Right"),
+    ("prev_song", "Alt+Ctrl+Up"),
+    ("next_song", "Alt+Ctrl+Down"),
+    ("toggle_lyrics_visibility", "Alt+H"),
+    ("next_lyrics_language", "Alt+Shift+L"),
+    ("lyrics_earlier", "Alt+Shift+Left"),
+    ("lyrics_later", "Alt+Shift+Right"),
+    ("save_lyrics_offset", "Alt+Shift+O"),
+    ("toggle_lyrics_recording", "Alt+Shift+K"),
+    ("stamp_lyrics_line", "Alt+Enter"),
+    ("undo_lyrics_stamp", "Alt+Backspace"),
+    ("prev_lyrics_line", "Alt+Shift+Up"),
+    ("next_lyrics_line", "Alt+Shift+Down"),
+    ("undo_playlist_edit", "Alt+Z"),
+    ("redo_playlist_edit", "Alt+Shift+Z"),
+    ("lyrics_top_left", "Alt+L+1"),
+    ("lyrics_top_center", "Alt+L+2"),
+    ("lyrics_top_right", "Alt+L+3"),
+    ("lyrics_center_left", "Alt+L+4"),
+    ("lyrics_center", "Alt+L+5"),
+    ("lyrics_center_right", "Alt+L+6"),
+    ("lyrics_bottom_left", "Alt+L+7"),
+    ("lyrics_bottom_center", "Alt+L+8"),
+    ("lyrics_bottom_right", "Alt+L+9"),
+    ("cycle_lyrics_monitor", "Alt+L+0"),
+    ("volume_increase", "Alt+Up"),
+    ("volume_decrease", "Alt+Down"),
+    ("rewind", "Alt+Left"),
+    ("fast_forward", "Alt+Right"),
+];
+
+
+pub enum KeybindingError {
+    Read(std::io::Error),
+    Write(std::io::Error),
+    Parse(serde_json::Error),
+    UnknownAction(String),
+    UnknownKey(&'static str, String),
+    UnknownGesture(&'static str, String),
+    NoLeader(&'static str),
+    LeaderGesture,
+    GestureSequence(&'static str),
+    GestureWithLeader(&'static str),
+    Conflict(&'static str, &'static str, String),
+}
+
+
+// Actions without a binding are disabled
+pub struct Keybindings(HashMap<&'static str, Binding>);
+
+
+#[derive(Debug)]
+enum BindingError {
+    UnknownKey(String),
+    UnknownGesture(String),
+    NoLeader,
+    GestureSequence,
+    GestureWithLeader, // a sequence only because the leader has several steps
+}
+
+
+#[inline(always)]
+fn parse_combination(s: &str) -> Result<Vec<Key>, String> {
+    s.split('+').map(|key| parse_key(key).ok_or_else(|| key.trim().to_string())).collect()
+}
+
+// A binding is a sequence of combinations separated by commas, e.g. "Alt+L, 1",
+// a single combination can be prefixed by a gesture, e.g. "hold: Alt+Right"
+fn parse_binding(s: &str, leader: Option<&[Vec<Key>]>) -> Result<Binding, BindingError> {
+    let (gesture, s) = match s.split_once(':') {
+        Some((prefix, rest)) => match prefix.trim().to_ascii_lowercase().as_str() {
+            "press" => (Gesture::Press, rest),
+            "tap" => (Gesture::Tap, rest),
+            "hold" => (Gesture::Hold, rest),
+            "double" => (Gesture::DoubleTap, rest),
+            _ => return Err(BindingError::UnknownGesture(prefix.trim().to_string())),
+        }
+        None => (Gesture::Press, s),
+    };
+    
+    let mut steps = Vec::new();
+    let mut with_leader = false;
+    
+    for step in s.split(',') {
+        if step.trim().eq_ignore_ascii_case(LEADER) {
+            steps.extend_from_slice(leader.ok_or(BindingError::NoLeader)?);
+            with_leader = true;
+        }
+        else {
+            steps.push(parse_combination(step).map_err(BindingError::UnknownKey)?);
+        }
+    }
+    
+    if gesture != Gesture::Press && steps.len() > 1 {
+        return Err(if with_leader && s.split(',').count() == 1 { BindingError::GestureWithLeader } else { BindingError::GestureSequence });
+    }
+    
+    Ok(Binding { gesture, steps })
+}
+
+#[inline(always)]
+fn same_keys(a: &[Key], b: &[Key]) -> bool {
+    a.iter().collect::<HashSet<_>>() == b.iter().collect()
+}
+
+// Bindings on the same keys conflict with the same gesture, or when one of them is a press,
+// which fires as soon as the keys are down before a tap, hold or double tap can be told apart.
+// A binding that is the start of a sequence conflicts with it too, e.g. "Alt+L" and "Alt+L, 1"
+#[inline(always)]
+fn conflicts(a: &Binding, b: &Binding) -> bool {
+    a.steps.iter().zip(&b.steps).all(|(a, b)| same_keys(a, b))
+        && (a.steps.len() != b.steps.len() || a.gesture == b.gesture || a.gesture == Gesture::Press || b.gesture == Gesture::Press)
+}
+
+
+impl Keybindings {
+    // Actions missing from the file keep their default binding, invalid ones fall back to it
+    pub fn load(path: &Path) -> (Self, Vec<KeybindingError>) {
+        let mut errors = Vec::new();
+        let mut configured = HashMap::new();
+        
+        match read_to_string(path) {
+            Ok(s) => match from_str::<BTreeMap<String, String>>(&s) {
+                Ok(map) => {
+                    for (action, binding) in map {
+                        if action == LEADER {
+                            configured.insert(LEADER, binding);
+                            continue;
+                        }
+                        
+                        match DEFAULT_KEYBINDINGS.iter().find(|&&(name, _)| name == action) {
+                            Some(&(name, _)) => { configured.insert(name, binding); }
+                            None => errors.push(KeybindingError::UnknownAction(action)),
+                        }
+                    }
+                }
+                Err(e) => errors.push(KeybindingError::Parse(e)),
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let defaults: BTreeMap<_, _> = DEFAULT_KEYBINDINGS.iter().copied().chain([(LEADER, "")]).collect();
+                
+                if let Err(e) = write(path, to_string_pretty(&defaults).expect("Failed to serialize")) {
+                    errors.push(KeybindingError::Write(e));
+                }
+            }
+            Err(e) => errors.push(KeybindingError::Read(e)),
+        }
+        
+        let leader = match configured.get(LEADER).filter(|binding| !binding.trim().is_empty()).map(|binding| parse_binding(binding, None)) {
+            Some(Ok(binding)) if binding.gesture == Gesture::Press => Some(binding.steps),
+            Some(Ok(_)) => { errors.push(KeybindingError::LeaderGesture); None }
+            Some(Err(BindingError::UnknownKey(key))) => { errors.push(KeybindingError::UnknownKey(LEADER, key)); None }
+            Some(Err(BindingError::UnknownGesture(gesture))) => { errors.push(KeybindingError::UnknownGesture(LEADER, gesture)); None }
+            Some(Err(BindingError::GestureSequence)) => { errors.push(KeybindingError::LeaderGesture); None }
+            Some(Err(_)) => { errors.push(KeybindingError::UnknownKey(LEADER, LEADER.to_string())); None }
+            None => None,
+        };
+        
+        let mut bindings: Vec<(&'static str, Binding, bool)> = Vec::new();
+        
+        for &(action, default) in DEFAULT_KEYBINDINGS {
+            let keys = match configured.get(action) {
+                Some(binding) if binding.trim().is_empty() => continue,
+                Some(binding) => match parse_binding(binding, leader.as_deref()) {
+                    Ok(keys) => Some(keys),
+                    Err(BindingError::UnknownKey(key)) => { errors.push(KeybindingError::UnknownKey(action, key)); None }
+                    Err(BindingError::UnknownGesture(gesture)) => { errors.push(KeybindingError::UnknownGesture(action, gesture)); None }
+                    Err(BindingError::NoLeader) => { errors.push(KeybindingError::NoLeader(action)); None }
+                    Err(BindingError::GestureSequence) => { errors.push(KeybindingError::GestureSequence(action)); None }
+                    Err(BindingError::GestureWithLeader) => { errors.push(KeybindingError::GestureWithLeader(action)); None }
+                }
+                None => None,
+            };
+            
+            let explicit = keys.is_some();
+            let keys = keys.unwrap_or_else(|| parse_binding(default, None).expect("Invalid default keybinding"));
+            
+            // a configured binding takes priority over a default one it conflicts with
+            if let Some(i) = bindings.iter().position(|(_, other, _)| conflicts(other, &keys)) {
+                let (other, _, other_explicit) = &bindings[i];
+                let (other, other_explicit) = (*other, *other_explicit);
+                let binding = configured.get(action).map_or(default, String::as_str).to_string();
+                
+                if explicit && !other_explicit {
+                    errors.push(KeybindingError::Conflict(action, other, binding));
+                    bindings.remove(i);
+                }
+                else {
+                    errors.push(KeybindingError::Conflict(other, action, binding));
+                    continue;
+                }
+            }
+            
+            bindings.push((action, keys, explicit));
+        }
+        
+        (Self(bindings.into_iter().map(|(action, keys, _)| (action, keys)).collect()), errors)
+    }
+    
+    #[inline(always)]
+    pub fn get(&self, action: &str) -> Option<&Binding> {
+        self.0.get(action)
+    }
+}
+
+
+impl Display for KeybindingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeybindingError::Read(e) => write!(f, "Failed to read keybindings: {}", e),
+            KeybindingError::Write(e) => write!(f, "Failed to write default keybindings: {}", e),
+            KeybindingError::Parse(e) => write!(f, "Failed to parse keybindings: {}", e),
+            KeybindingError::UnknownAction(action) => write!(f, "Unknown action \"{}\" in keybindings", action),
+            KeybindingError::UnknownKey(action, key) => write!(f, "Unknown key \"{}\" in keybinding of {}, using the default instead", key, action),
+            KeybindingError::UnknownGesture(action, gesture) => write!(f, "Unknown gesture \"{}\" in keybinding of {}, expected press, tap, hold or double, using the default instead", gesture, action),
+            KeybindingError::NoLeader(action) => write!(f, "Keybinding of {} uses the leader but no leader is set, using the default instead", action),
+            KeybindingError::LeaderGesture => write!(f, "The leader cannot have a gesture as the rest of a binding follows it, no leader is used"),
+            KeybindingError::GestureSequence(action) => write!(f, "Keybinding of {} has a gesture but is a sequence, using the default instead", action),
+            KeybindingError::GestureWithLeader(action) => write!(f, "Keybinding of {} has a gesture but the leader it uses is a sequence, using the default instead", action),
+            KeybindingError::Conflict(kept, disabled, binding) => write!(f, "{} and {} conflict on {}, {} is disabled", kept, disabled, binding, disabled),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn binding(s: &str) -> Binding {
+        parse_binding(s, None).expect("Invalid keybinding")
+    }
+    
+    #[test]
+    fn gestures_on_the_same_keys() {
+        assert!(conflicts(&binding("Alt+L"), &binding("L+Alt")));
+        assert!(conflicts(&binding("Alt+L"), &binding("hold: Alt+L")));
+        assert!(conflicts(&binding("tap: Alt+L"), &binding("press: Alt+L")));
+        assert!(conflicts(&binding("hold: Alt+L"), &binding("hold: Alt+L")));
+        assert!(!conflicts(&binding("tap: Alt+L"), &binding("hold: Alt+L")));
+        assert!(!conflicts(&binding("tap: Alt+L"), &binding("double: Alt+L")));
+        assert!(!conflicts(&binding("Alt+L"), &binding("Alt+K")));
+    }
+    
+    #[test]
+    fn start_of_a_sequence() {
+        assert!(conflicts(&binding("Alt+L"), &binding("Alt+L, 1")));
+        assert!(conflicts(&binding("Alt+L, 1, 2"), &binding("Alt+L, 1")));
+        assert!(conflicts(&binding("hold: Alt+L"), &binding("Alt+L, 1")));
+        assert!(!conflicts(&binding("Alt+L, 1"), &binding("Alt+L, 2")));
+        assert!(!conflicts(&binding("Alt+K"), &binding("Alt+L, 1")));
+        
+        let leader = binding("Alt+L").steps;
+        
+        assert!(conflicts(&binding("Alt+L"), &parse_binding("Leader, 1", Some(&leader)).expect("Invalid keybinding")));
+    }
+    
+    #[test]
+    fn binding_errors() {
+        let leader = binding("Alt+L, 1").steps;
+        
+        assert!(matches!(parse_binding("tripletap: K", None), Err(BindingError::UnknownGesture(g)) if g == "tripletap"));
+        assert!(matches!(parse_binding("tap: Alt+Nope", None), Err(BindingError::UnknownKey(k)) if k == "Nope"));
+        assert!(matches!(parse_binding("Leader, 2", None), Err(BindingError::NoLeader)));
+        assert!(matches!(parse_binding("hold: Alt+K, 1", None), Err(BindingError::GestureSequence)));
+        assert!(matches!(parse_binding("hold: Leader", Some(&leader)), Err(BindingError::GestureWithLeader)));
+        assert!(matches!(parse_binding("hold: Leader, 2", Some(&leader)), Err(BindingError::GestureSequence)));
+        assert!(parse_binding("hold: Leader", Some(&binding("Alt+L").steps)).is_ok());
+    }
+    
+    #[test]
+    fn default_keybindings() {
+        let bindings: Vec<_> = DEFAULT_KEYBINDINGS.iter().map(|&(_, s)| binding(s)).collect();
+        
+        for (i, a) in bindings.iter().enumerate() {
+            for (j, b) in bindings.iter().enumerate().skip(i + 1) {
+                assert!(!conflicts(a, b), "{} and {} conflict", DEFAULT_KEYBINDINGS[i].0, DEFAULT_KEYBINDINGS[j].0);
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::{fs::{read_to_string, OpenOptions}, io::{self, Write}, path::PathBuf, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+use serde_derive::{Serialize, Deserialize};
+use serde_json::{from_str, to_string, to_string_pretty};
+
+use crate::error;
+
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ListeningEntry {
+    pub path: String,
+    pub start: u64, // seconds since unix epoch
+    pub listened: f64, // seconds
+    pub completed: bool,
+}
+
+
+struct ListeningSession {
+    path: String,
+    start: SystemTime,
+    listened: Duration,
+}
+
+
+pub struct ListeningLog {
+    path: PathBuf,
+    session: Option<ListeningSession>,
+}
+
+
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+
+impl ListeningLog {
+    #[inline(always)]
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            session: None,
+        }
+    }
+    
+    #[inline(always)]
+    pub fn start(&mut self, song: &str) {
+        self.finish(false);
+        
+        self.session.replace(ListeningSession {
+            path: song.to_string(),
+            start: SystemTime::now(),
+            listened: Duration::ZERO,
+        });
+    }
+    
+    #[inline(always)]
+    pub fn tick(&mut self, delta: Duration) {
+        if let Some(session) = &mut self.session {
+            session.listened += delta;
+        }
+    }
+    
+    pub fn finish(&mut self, completed: bool) {
+        if let Some(session) = self.session.take() {
+            let entry = ListeningEntry {
+                path: session.path,
+                start: session.start.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+                listened: session.listened.as_secs_f64(),
+                completed,
+            };
+            
+            let result = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .and_then(|mut file| writeln!(file, "{}", to_string(&entry).expect("Failed to serialize")));
+            
+            if let Err(e) = result {
+                error!(e, "Failed to write listening log");
+            }
+        }
+    }
+    
+    // entries started within [from, to) in seconds since unix epoch
+    pub fn read(&self, from: u64, to: u64) -> io::Result<Vec<ListeningEntry>> {
+        Ok(read_to_string(&self.path)?
+            .lines()
+            .filter_map(|line| from_str::<ListeningEntry>(line).inspect_err(|e| error!(e, "Invalid listening log entry")).ok())
+            .filter(|entry| from <= entry.start && entry.start < to)
+            .collect())
+    }
+}
+
+
+impl ExportFormat {
+    #[inline(always)]
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+    
+    pub fn export(&self, entries: &[ListeningEntry]) -> String {
+        match self {
+            ExportFormat::Csv => {
+                let mut s = String::from("start,path,listened,completed\n");
+                
+                for entry in entries {
+                    s += &format!(
+                        "{},\"{}\",{:.3},{}\n",
+                        format_timestamp(entry.start),
+                        entry.path.replace('"', "\"\""),
+                        entry.listened,
+                        entry.completed,
+                    );
+                }
+                
+                s
+            }
+            ExportFormat::Json => to_string_pretty(entries).expect("Failed to serialize"),
+        }
+    }
+}
+
+
+// days since 1970-01-01 from a proleptic gregorian date
+#[inline(always)]
+fn days_from_civil(y: i64, m: u64, d: u64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = (y - era * 400) as u64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    
+    era * 146097 + doe as i64 - 719468
+}
+
+#[inline(always)]
+fn civil_from_days(z: i64) -> (i64, u64, u64) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = (z - era * 146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    
+    (yoe as i64 + era * 400 + (m <= 2) as i64, m, d)
+}
+
+#[inline(always)]
+fn days_in_month(y: i64, m: u64) -> u64 {
+    match m {
+        2 if y % 4 == 0 && (y % 100 != 0 || y % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// parses YYYY-MM-DD into seconds since unix epoch (UTC midnight)
+pub fn parse_date(s: &str) -> Option<u64> {
+    let mut parts = s.splitn(3, '-');
+    let y = parts.next()?.parse().ok()?;
+    let m = parts.next()?.parse().ok().filter(|m| (1..=12).contains(m))?;
+    let d = parts.next()?.parse().ok().filter(|d| (1..=days_in_month(y, m)).contains(d))?;
+    
+    u64::try_from(days_from_civil(y, m, d)).ok().map(|days| days * SECONDS_PER_DAY)
+}
+
+#[inline(always)]
+pub fn end_of_date(s: &str) -> Option<u64> {
+    parse_date(s).map(|t| t + SECONDS_PER_DAY)
+}
+
+// formats seconds since unix epoch as ISO 8601 in UTC
+pub fn format_timestamp(t: u64) -> String {
+    let (y, m, d) = civil_from_days((t / SECONDS_PER_DAY) as i64);
+    let s = t % SECONDS_PER_DAY;
+    
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", y, m, d, s / 3600, s / 60 % 60, s % 60)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-03-01").map(format_timestamp).as_deref(), Some("2024-03-01T00:00:00Z"));
+        assert_eq!(end_of_date("2024-12-31"), parse_date("2025-01-01"));
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("2024-04-31"), None);
+        assert_eq!(parse_date("2024-01-00"), None);
+        assert_eq!(parse_date("2024-01"), None);
+    }
+    
+    #[test]
+    fn leap_years() {
+        assert!(parse_date("2024-02-29").is_some());
+        assert!(parse_date("2000-02-29").is_some());
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2100-02-29"), None);
+        assert_eq!(parse_date("2024-02-31"), None);
+    }
+}
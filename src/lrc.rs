@@ -0,0 +1,261 @@
+use std::{collections::BTreeMap, fmt::Display, fs::{read_to_string, write}, path::{Path, PathBuf}, time::Duration};
+
+
+#[derive(Default)]
+pub struct Lrc {
+    pub tags: BTreeMap<String, String>, // e.g. ti, ar, al, by
+    pub offset: i64, // in milliseconds, positive shows the lyrics earlier
+    pub lines: Vec<(Duration, String)>,
+}
+
+
+#[inline(always)]
+pub fn format_timestamp(time: Duration) -> String {
+    let ms = time.as_millis();
+    
+    format!("{:02}:{:02}.{:02}", ms / 60000, ms / 1000 % 60, ms / 10 % 100)
+}
+
+// Accepts mm:ss, mm:ss.xx, mm:ss.xxx and mm:ss:xx
+#[inline(always)]
+fn parse_timestamp(s: &str) -> Option<Duration> {
+    let (m, rest) = s.split_once(':')?;
+    let (s, fraction) = rest.split_once(['.', ':']).unwrap_or((rest, ""));
+    
+    if m.is_empty() || s.is_empty() || !m.bytes().chain(s.bytes()).chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    
+    let millis = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<u64>().ok()? * 100,
+        2 => fraction.parse::<u64>().ok()? * 10,
+        _ => fraction[..3].parse().ok()?,
+    };
+    
+    Some(Duration::from_millis((m.parse::<u64>().ok()? * 60 + s.parse::<u64>().ok()?) * 1000 + millis))
+}
+
+
+impl Lrc {
+    pub fn parse(s: &str) -> Self {
+        let mut lrc = Lrc::default();
+        
+        for line in s.lines() {
+            let mut rest = line.trim();
+            let mut times = Vec::new();
+            
+            // a line can have several timestamps, e.g. [00:12.00][01:30.00]chorus
+            while let Some((tag, after)) = rest.strip_prefix('[').and_then(|s| s.split_once(']')) {
+                match parse_timestamp(tag.trim()) {
+                    Some(time) => times.push(time),
+                    None if times.is_empty() => {
+                        if let Some((key, value)) = tag.split_once(':') {
+                            let key = key.trim().to_ascii_lowercase();
+                            
+                            if key == "offset" {
+                                lrc.offset = value.trim().trim_start_matches('+').parse().unwrap_or(0);
+                            }
+                            else {
+                                lrc.tags.insert(key, value.trim().to_string());
+                            }
+                        }
+                    }
+                    None => break,
+                }
+                
+                rest = after;
+            }
+            
+            lrc.lines.extend(times.into_iter().map(|time| (time, rest.trim().to_string())));
+        }
+        
+        lrc.lines.sort_by_key(|&(time, _)| time);
+        
+        lrc
+    }
+    
+    #[inline(always)]
+    pub fn read(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        read_to_string(path).map(|s| Lrc::parse(&s))
+    }
+    
+    // Adds to the [offset:] tag of a file, keeping everything else as it is, line endings included
+    pub fn add_offset(path: impl AsRef<Path>, offset: i64) -> std::io::Result<()> {
+        let s = read_to_string(path.as_ref())?;
+        
+        write(path, Lrc::with_offset(&s, offset))
+    }
+    
+    #[inline(always)]
+    fn with_offset(s: &str, offset: i64) -> String {
+        let newline = if s.contains("\r\n") { "\r\n" } else { "\n" };
+        let total = Lrc::parse(s).offset + offset;
+        let tag = format!("[offset:{:+}]", total);
+        let mut lines: Vec<&str> = s.lines().collect();
+        
+        match lines.iter().position(|line| line.trim_start().get(..8).is_some_and(|t| t.eq_ignore_ascii_case("[offset:"))) {
+            Some(i) => lines[i] = &tag,
+            None => lines.insert(0, &tag),
+        }
+        
+        lines.join(newline) + newline
+    }
+    
+    // Looks for <basename><ext> with each extension, next to the song and then in the shared lyrics directory
+    fn find_with(song: &Path, lyrics_dir: Option<&Path>, exts: &[String]) -> Option<PathBuf> {
+        let stem = song.file_stem()?;
+        let dirs = song.parent().into_iter().chain(lyrics_dir);
+        
+        dirs.flat_map(|dir| exts.iter().map(move |ext| {
+            let mut name = stem.to_os_string();
+            
+            name.push(ext);
+            
+            dir.join(name)
+        })).find(|path| path.is_file())
+    }
+    
+    // Looks for <basename>.<lang>.lrc in the preferred languages and then <basename>.lrc
+    #[inline(always)]
+    pub fn find(song: impl AsRef<Path>, lyrics_dir: Option<&Path>, languages: &[String]) -> Option<PathBuf> {
+        let exts: Vec<_> = languages.iter().map(|lang| format!(".{}.lrc", lang)).chain([".lrc".to_string()]).collect();
+        
+        Lrc::find_with(song.as_ref(), lyrics_dir, &exts)
+    }
+    
+    // Looks for <basename>.<lang>.lrc only
+    #[inline(always)]
+    pub fn find_translation(song: impl AsRef<Path>, lyrics_dir: Option<&Path>, lang: &str) -> Option<PathBuf> {
+        Lrc::find_with(song.as_ref(), lyrics_dir, &[format!(".{}.lrc", lang)])
+    }
+    
+    // Lines with the offset applied, in the same form as SYLT lines which start with a newline,
+    // each word of Enhanced LRC (e.g. [00:12.00]<00:12.00>Hello <00:12.50>world) is a separate entry
+    pub fn synced_lyrics(&self) -> Vec<(Duration, String)> {
+        let offset = |time: Duration| if self.offset >= 0 {
+            time.saturating_sub(Duration::from_millis(self.offset as u64))
+        }
+        else {
+            time + Duration::from_millis(self.offset.unsigned_abs())
+        };
+        
+        let mut lyrics = Vec::new();
+        
+        // a line with several timestamps repeats its words relative to the first of them
+        let mut first = std::collections::HashMap::new();
+        
+        for (time, s) in &self.lines {
+            let first = *first.entry(s).or_insert(*time);
+            let mut entries = Vec::new();
+            let mut rest = s.as_str();
+            let mut word_time = *time;
+            
+            while let Some(i) = rest.find('<') {
+                let Some((tag, after)) = rest[i + 1..].split_once('>') else { break };
+                let Some(t) = parse_timestamp(tag.trim()) else { break };
+                
+                entries.push((word_time, &rest[..i]));
+                word_time = (t + *time).saturating_sub(first);
+                rest = after;
+            }
+            
+            entries.push((word_time, rest));
+            
+            let mut entries = entries.into_iter().filter(|(_, s)| !s.is_empty());
+            let (time, s) = entries.next().unwrap_or((*time, ""));
+            
+            lyrics.push((offset(time), format!("\n{}", s)));
+            lyrics.extend(entries.map(|(time, s)| (offset(time), s.to_string())));
+        }
+        
+        lyrics.sort_by_key(|&(time, _)| time);
+        
+        lyrics
+    }
+}
+
+
+impl Display for Lrc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (key, value) in &self.tags {
+            writeln!(f, "[{}:{}]", key, value)?;
+        }
+        
+        if self.offset != 0 {
+            writeln!(f, "[offset:{:+}]", self.offset)?;
+        }
+        
+        for (time, s) in &self.lines {
+            writeln!(f, "[{}]{}", format_timestamp(*time), s)?;
+        }
+        
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+    
+    #[test]
+    fn several_timestamps() {
+        let lrc = Lrc::parse("[00:30.00]verse\n[00:12.00][01:30.00]chorus");
+        
+        assert_eq!(lrc.lines, [(ms(12000), "chorus".to_string()), (ms(30000), "verse".to_string()), (ms(90000), "chorus".to_string())]);
+    }
+    
+    #[test]
+    fn offset() {
+        assert_eq!(Lrc::parse("[offset:+500]").offset, 500);
+        assert_eq!(Lrc::parse("[offset:-250]").offset, -250);
+        assert_eq!(Lrc::parse("[offset:500]\n[00:01.00]a").synced_lyrics(), [(ms(500), "\na".to_string())]);
+        assert_eq!(Lrc::parse("[offset:-250]\n[00:01.00]a").synced_lyrics(), [(ms(1250), "\na".to_string())]);
+    }
+    
+    #[test]
+    fn add_offset_keeps_line_endings() {
+        assert_eq!(Lrc::with_offset("[ti:a]\r\n[00:01.00]b\r\n", 100), "[offset:+100]\r\n[ti:a]\r\n[00:01.00]b\r\n");
+        assert_eq!(Lrc::with_offset("[ti:a]\r\n[offset:+100]\r\n[00:01.00]b", -300), "[ti:a]\r\n[offset:-200]\r\n[00:01.00]b\r\n");
+        assert_eq!(Lrc::with_offset("[00:01.00]b\n", 100), "[offset:+100]\n[00:01.00]b\n");
+    }
+    
+    #[test]
+    fn timestamp_forms() {
+        assert_eq!(parse_timestamp("01:02"), Some(ms(62000)));
+        assert_eq!(parse_timestamp("01:02.5"), Some(ms(62500)));
+        assert_eq!(parse_timestamp("01:02.50"), Some(ms(62500)));
+        assert_eq!(parse_timestamp("01:02:50"), Some(ms(62500)));
+        assert_eq!(parse_timestamp("01:02.123"), Some(ms(62123)));
+        assert_eq!(parse_timestamp("ar:Someone"), None);
+        assert_eq!(parse_timestamp(":02.00"), None);
+    }
+    
+    #[test]
+    fn tags_and_brackets() {
+        let lrc = Lrc::parse("[ar:Someone]\n[Ti: Title ]\n[00:01.00][chorus]sing\n[00:02.00]");
+        
+        assert_eq!(lrc.tags.get("ar").map(String::as_str), Some("Someone"));
+        assert_eq!(lrc.tags.get("ti").map(String::as_str), Some("Title"));
+        assert_eq!(lrc.tags.len(), 2);
+        assert_eq!(lrc.lines, [(ms(1000), "[chorus]sing".to_string()), (ms(2000), String::new())]);
+    }
+    
+    #[test]
+    fn word_timing() {
+        let lrc = Lrc::parse("[00:12.00][01:00.00]<00:12.00>Hello <00:12.50>world\n[00:20.00]plain");
+        
+        assert_eq!(lrc.synced_lyrics(), [
+            (ms(12000), "\nHello ".to_string()),
+            (ms(12500), "world".to_string()),
+            (ms(20000), "\nplain".to_string()),
+            (ms(60000), "\nHello ".to_string()),
+            (ms(60500), "world".to_string()),
+        ]);
+    }
+}
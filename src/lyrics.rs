@@ -5,14 +5,43 @@ use serde_derive::*;
 use crate::BooleanConditional;
 
 
-#[cfg(not(any(feature = "x11-lyrics", feature = "windows-lyrics")))]
-pub type Lyrics = Dummy;
+// Which lyrics are shown, only the ones compiled in are available
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LyricsBackend {
+    Auto, // the floating lyrics of the platform, or none
+    X11,
+    Wayland,
+    Windows,
+    Console,
+    None,
+}
 
-#[cfg(feature = "x11-lyrics")]
-pub type Lyrics = xosd::XosdLyrics;
+// The backends that keep a lot of state are boxed so that the others stay small
+pub enum Lyrics {
+    #[cfg(feature = "x11-lyrics")]
+    X11(Box<xosd::XosdLyrics>),
+    #[cfg(feature = "wayland-lyrics")]
+    Wayland(Box<wayland::WaylandLyrics>),
+    #[cfg(feature = "windows-lyrics")]
+    Windows(windows::WindowsLyrics),
+    #[cfg(feature = "console")]
+    Console(console::ConsoleLyrics),
+    None(Dummy),
+}
 
-#[cfg(feature = "windows-lyrics")]
-pub type Lyrics = windows::WindowsLyrics;
+pub enum LyricsError {
+    #[cfg(feature = "x11-lyrics")]
+    X11(<xosd::XosdLyrics as LyricsTrait>::Error),
+    #[cfg(feature = "wayland-lyrics")]
+    Wayland(<wayland::WaylandLyrics as LyricsTrait>::Error),
+    #[cfg(feature = "windows-lyrics")]
+    Windows(<windows::WindowsLyrics as LyricsTrait>::Error),
+    #[cfg(feature = "console")]
+    Console(<console::ConsoleLyrics as LyricsTrait>::Error),
+    None(DummyError),
+    Unavailable(LyricsBackend),
+}
 
 
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -28,11 +57,43 @@ pub enum LyricsPosition {
     BottomRight,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+// Colors are "#RRGGBB", x11 also accepts color names
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyricsStyle {
+    pub font: String, // font family, empty for the default font
+    pub size: i32, // of the current line
+    pub small_size: i32, // of the other lines and translations
+    pub color: String,
+    pub context_color: String, // of the previous and next lines
+    pub sung_color: String,
+    pub translation_color: String,
+    pub outline: i32,
+    pub outline_color: String,
+    pub shadow: i32,
+    pub shadow_color: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LyricsLayout {
     pub position: LyricsPosition,
     pub margin: i32,
     pub visible: bool,
+    #[serde(default)]
+    pub style: LyricsStyle,
+    // lines shown before and after the current line, 0 and 0 for a single line
+    #[serde(default = "default_context_lines")]
+    pub lines_before: usize,
+    #[serde(default = "default_context_lines")]
+    pub lines_after: usize,
+    // index of the monitor, the first one is used if there are fewer monitors
+    #[serde(default)]
+    pub monitor: usize,
+    // from the top left corner of the monitor, replace the position and margin on that axis when set
+    #[serde(default)]
+    pub x: Option<i32>,
+    #[serde(default)]
+    pub y: Option<i32>,
 }
 
 
@@ -42,14 +103,67 @@ pub trait LyricsTrait {
     fn new(layout: LyricsLayout) -> std::result::Result<Self, Self::Error> where Self: Sized;
     #[inline(always)]
     fn set_lyrics(&mut self, _lyrics: Vec<(Duration, String)>) -> Result<(), Self::Error> { Ok(()) }
+    // Each translation is shown under the current line, set after the lyrics
+    #[inline(always)]
+    fn set_translations(&mut self, _translations: Vec<Vec<(Duration, String)>>) -> Result<(), Self::Error> { Ok(()) }
     #[inline(always)]
     fn clear(&mut self) -> Result<(), Self::Error> { Ok(()) }
     #[inline(always)]
     fn update(&mut self, _time: Duration) -> Result<(), Self::Error> { Ok(()) }
     #[inline(always)]
-    fn set_layout(&mut self, _layout: LyricsLayout) -> Result<(), Self::Error> { Ok(()) }
+    fn set_layout(&mut self, _layout: &LyricsLayout) -> Result<(), Self::Error> { Ok(()) }
     #[inline(always)]
     fn refresh(&mut self) -> Result<(), Self::Error> { Ok(()) }
+    // Number of monitors the lyrics can be placed on
+    #[inline(always)]
+    fn monitor_count(&self) -> usize { 1 }
+    // Reads the monitors again for the backends that keep a list of them
+    #[inline(always)]
+    fn refresh_monitors(&mut self) {}
+}
+
+
+// Parses "#RRGGBB" for the backends that do not know color names
+#[allow(dead_code)]
+#[inline(always)]
+fn rgb(color: &str) -> Option<u32> {
+    color.strip_prefix('#').filter(|hex| hex.len() == 6).and_then(|hex| u32::from_str_radix(hex, 16).ok())
+}
+
+#[inline(always)]
+pub fn default_context_lines() -> usize {
+    1
+}
+
+impl LyricsStyle {
+    #[inline(always)]
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.size <= 0 || self.small_size <= 0 {
+            Err("lyrics font sizes must be greater than 0")
+        }
+        else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for LyricsStyle {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            font: String::new(),
+            size: 32,
+            small_size: 24,
+            color: "#FFFFFF".to_string(),
+            context_color: "#A9A9A9".to_string(),
+            sung_color: "#FFD700".to_string(),
+            translation_color: "#D3D3D3".to_string(),
+            outline: 2,
+            outline_color: "#000000".to_string(),
+            shadow: 0,
+            shadow_color: "#000000".to_string(),
+        }
+    }
 }
 
 
@@ -72,10 +186,227 @@ impl Display for DummyError {
     }
 }
 
+macro_rules! dispatch {
+    ($self:ident, $lyrics:ident => $e:expr) => {
+        match $self {
+            #[cfg(feature = "x11-lyrics")]
+            Lyrics::X11($lyrics) => $e.map_err(LyricsError::X11),
+            #[cfg(feature = "wayland-lyrics")]
+            Lyrics::Wayland($lyrics) => $e.map_err(LyricsError::Wayland),
+            #[cfg(feature = "windows-lyrics")]
+            Lyrics::Windows($lyrics) => $e.map_err(LyricsError::Windows),
+            #[cfg(feature = "console")]
+            Lyrics::Console($lyrics) => $e.map_err(LyricsError::Console),
+            Lyrics::None($lyrics) => $e.map_err(LyricsError::None),
+        }
+    };
+    ($self:ident, $lyrics:ident -> $e:expr) => {
+        match $self {
+            #[cfg(feature = "x11-lyrics")]
+            Lyrics::X11($lyrics) => $e,
+            #[cfg(feature = "wayland-lyrics")]
+            Lyrics::Wayland($lyrics) => $e,
+            #[cfg(feature = "windows-lyrics")]
+            Lyrics::Windows($lyrics) => $e,
+            #[cfg(feature = "console")]
+            Lyrics::Console($lyrics) => $e,
+            Lyrics::None($lyrics) => $e,
+        }
+    };
+}
+
+impl LyricsBackend {
+    // Wayland is preferred in a Wayland session, X11 still works there through XWayland
+    #[inline(always)]
+    fn resolve(self) -> Self {
+        match self {
+            LyricsBackend::Auto if cfg!(feature = "wayland-lyrics") && std::env::var_os("WAYLAND_DISPLAY").is_some() => LyricsBackend::Wayland,
+            LyricsBackend::Auto if cfg!(feature = "x11-lyrics") => LyricsBackend::X11,
+            LyricsBackend::Auto if cfg!(feature = "windows-lyrics") => LyricsBackend::Windows,
+            LyricsBackend::Auto => LyricsBackend::None,
+            backend => backend,
+        }
+    }
+}
+
+impl Lyrics {
+    pub fn with_backend(backend: LyricsBackend, layout: LyricsLayout) -> Result<Self, LyricsError> {
+        match backend.resolve() {
+            #[cfg(feature = "x11-lyrics")]
+            LyricsBackend::X11 => xosd::XosdLyrics::new(layout).map(Box::new).map(Lyrics::X11).map_err(LyricsError::X11),
+            #[cfg(feature = "wayland-lyrics")]
+            LyricsBackend::Wayland => match wayland::WaylandLyrics::new(layout.clone()) {
+                Ok(lyrics) => Ok(Lyrics::Wayland(Box::new(lyrics))),
+                // e.g. GNOME has no layer shell, X11 lyrics still work there through XWayland
+                #[cfg(feature = "x11-lyrics")]
+                Err(e) if backend == LyricsBackend::Auto => {
+                    crate::error!(e, "Failed to initialize Wayland lyrics, falling back to X11");
+                    
+                    xosd::XosdLyrics::new(layout).map(Box::new).map(Lyrics::X11).map_err(LyricsError::X11)
+                }
+                Err(e) => Err(LyricsError::Wayland(e)),
+            },
+            #[cfg(feature = "windows-lyrics")]
+            LyricsBackend::Windows => windows::WindowsLyrics::new(layout).map(Lyrics::Windows).map_err(LyricsError::Windows),
+            #[cfg(feature = "console")]
+            LyricsBackend::Console => console::ConsoleLyrics::new(layout).map(Lyrics::Console).map_err(LyricsError::Console),
+            LyricsBackend::None => Dummy::new(layout).map(Lyrics::None).map_err(LyricsError::None),
+            #[allow(unreachable_patterns)]
+            _ => Err(LyricsError::Unavailable(backend)),
+        }
+    }
+}
+
+impl LyricsTrait for Lyrics {
+    type Error = LyricsError;
+    
+    #[inline(always)]
+    fn new(layout: LyricsLayout) -> Result<Self, Self::Error> {
+        Lyrics::with_backend(LyricsBackend::Auto, layout)
+    }
+    
+    #[inline(always)]
+    fn set_lyrics(&mut self, lyrics: Vec<(Duration, String)>) -> Result<(), Self::Error> {
+        dispatch!(self, l => l.set_lyrics(lyrics))
+    }
+    
+    #[inline(always)]
+    fn set_translations(&mut self, translations: Vec<Vec<(Duration, String)>>) -> Result<(), Self::Error> {
+        dispatch!(self, l => l.set_translations(translations))
+    }
+    
+    #[inline(always)]
+    fn clear(&mut self) -> Result<(), Self::Error> {
+        dispatch!(self, l => l.clear())
+    }
+    
+    #[inline(always)]
+    fn update(&mut self, time: Duration) -> Result<(), Self::Error> {
+        dispatch!(self, l => l.update(time))
+    }
+    
+    #[inline(always)]
+    fn set_layout(&mut self, layout: &LyricsLayout) -> Result<(), Self::Error> {
+        dispatch!(self, l => l.set_layout(layout))
+    }
+    
+    #[inline(always)]
+    fn refresh(&mut self) -> Result<(), Self::Error> {
+        dispatch!(self, l => l.refresh())
+    }
+    
+    #[inline(always)]
+    fn monitor_count(&self) -> usize {
+        dispatch!(self, l -> l.monitor_count())
+    }
+    
+    #[inline(always)]
+    fn refresh_monitors(&mut self) {
+        dispatch!(self, l -> l.refresh_monitors())
+    }
+}
+
+impl Display for LyricsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(feature = "x11-lyrics")]
+            LyricsError::X11(e) => Display::fmt(e, f),
+            #[cfg(feature = "wayland-lyrics")]
+            LyricsError::Wayland(e) => Display::fmt(e, f),
+            #[cfg(feature = "windows-lyrics")]
+            LyricsError::Windows(e) => Display::fmt(e, f),
+            #[cfg(feature = "console")]
+            LyricsError::Console(e) => Display::fmt(e, f),
+            LyricsError::None(e) => Display::fmt(e, f),
+            LyricsError::Unavailable(backend) => write!(f, "{:?} lyrics are not available in this build", backend),
+        }
+    }
+}
+
+// A line and the time and byte index of each of its words (a single word for lines without word timing)
+pub struct Line {
+    pub text: String,
+    pub words: Vec<(Duration, usize)>,
+}
+
+pub const ESTIMATED_MARK: &str = "~ ";
+
+// How far apart the start of a line and its translation can be
+#[cfg(any(feature = "x11-lyrics", feature = "wayland-lyrics", feature = "windows-lyrics", feature = "console"))]
+const TRANSLATION_TOLERANCE: Duration = Duration::from_secs(1);
+
 #[derive(Default)]
 pub struct Lines {
-    lines: Vec<(Duration, String)>,
+    lines: Vec<Line>,
+    translations: Vec<Vec<Option<String>>>, // the translation of each line in every track
     index: Option<usize>,
+    sung: usize, // number of words sung in the current line
+}
+
+impl Line {
+    #[inline(always)]
+    pub fn start(&self) -> Duration {
+        self.words[0].0
+    }
+    
+    // Joins entries into lines whichever SYLT convention they follow, a newline at the start of an entry starts
+    // a new line and one at the end ends the line, other entries are words of the line they are in.
+    // Without any newline every entry is a line. An entry of only whitespace after a line break is a spacer
+    // that ends the line before it with an empty line, e.g. a blank line of .lrc at an instrumental break
+    pub fn group(lyrics: Vec<(Duration, String)>) -> Vec<Line> {
+        let mut lines: Vec<Line> = Vec::new();
+        let has_newlines = lyrics.iter().any(|(_, s)| s.contains(['\n', '\r']));
+        let mut new_line = true;
+        
+        for (time, s) in lyrics {
+            let s = s.replace("\r\n", "\n").replace('\r', "\n");
+            
+            new_line |= !has_newlines;
+            
+            if s.trim().is_empty() && (new_line || s.contains('\n')) {
+                if lines.last().is_some_and(|line| !line.text.is_empty()) {
+                    lines.push(Line { text: String::new(), words: vec![(time, 0)] });
+                }
+                
+                new_line = true;
+                continue;
+            }
+            
+            for (i, part) in s.split('\n').enumerate() {
+                new_line |= i > 0;
+                
+                match lines.last_mut().filter(|_| !new_line) {
+                    // a space between words still separates them
+                    Some(line) if part.trim().is_empty() => line.text.push_str(part),
+                    Some(line) => {
+                        line.words.push((time, line.text.len()));
+                        line.text.push_str(part);
+                    }
+                    None if part.trim().is_empty() => {}
+                    None => {
+                        lines.push(Line { text: part.trim_start().to_string(), words: vec![(time, 0)] });
+                        new_line = false;
+                    }
+                }
+            }
+        }
+        
+        lines
+    }
+}
+
+// Unsynced lyrics timed as if every line took as long, a blank line between verses takes as long as a line,
+// the lines are spread over the length of the song unless the duration of a line is given.
+// Every line starts with ESTIMATED_MARK so that every backend shows that the timing is a guess
+pub fn estimate_timing(text: &str, length: Duration, line_duration: Duration) -> Vec<(Duration, String)> {
+    let lines: Vec<_> = text.trim().lines().map(str::trim).collect();
+    let step = if line_duration.is_zero() { length / lines.len().max(1) as u32 } else { line_duration };
+    
+    lines.into_iter()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| (step * i as u32, format!("\n{}{}", ESTIMATED_MARK, line)))
+        .collect()
 }
 
 impl Lines {
@@ -87,14 +418,57 @@ impl Lines {
     #[inline(always)]
     pub fn set(&mut self, lyrics: Vec<(Duration, String)>) {
         self.index.replace(0);
-        self.lines = lyrics;
+        self.sung = 0;
+        self.lines = Line::group(lyrics);
+        self.translations.clear();
     }
     
     #[inline(always)]
     pub fn update(&mut self, time: Duration) -> bool {
-        let i = self.lines.partition_point(|&(t, _)| time > t);
+        let i = self.lines.partition_point(|line| time > line.start());
+        let sung = i.checked_sub(1).map_or(0, |i| self.lines[i].words.partition_point(|&(t, _)| time > t));
         
-        self.index.is_some_and(|index| i != index).ifdo(|| { self.index.replace(i); })
+        self.index.is_some_and(|index| i != index || sung != self.sung).ifdo(|| { self.index.replace(i); self.sung = sung; })
+    }
+    
+    #[inline(always)]
+    pub fn get(&self) -> &[Line] {
+        &self.lines
+    }
+    
+    // The index of the current line, None before the first line
+    #[inline(always)]
+    pub fn current(&self) -> Option<usize> {
+        self.index.filter(|&i| i > 0).map(|i| i - 1)
+    }
+}
+
+// What the floating lyrics backends read
+#[cfg(any(feature = "x11-lyrics", feature = "wayland-lyrics", feature = "windows-lyrics", feature = "console"))]
+impl Lines {
+    // Each line gets the translated line that starts closest to it, if it is close enough
+    pub fn set_translations(&mut self, translations: Vec<Vec<(Duration, String)>>) {
+        self.translations = translations.into_iter().map(|track| {
+            let track = Line::group(track);
+            
+            self.lines.iter().map(|line| {
+                track.iter()
+                    .min_by_key(|t| t.start().abs_diff(line.start()))
+                    .filter(|t| t.start().abs_diff(line.start()) <= TRANSLATION_TOLERANCE)
+                    .map(|t| t.text.clone())
+            }).collect()
+        }).collect();
+    }
+    
+    #[inline(always)]
+    pub fn translation_count(&self) -> usize {
+        self.translations.len()
+    }
+    
+    // The translation of the current line in a track
+    #[inline(always)]
+    pub fn translation(&self, track: usize) -> Option<&str> {
+        self.index.filter(|&i| i > 0).and_then(|i| self.translations.get(track)?[i - 1].as_deref())
     }
     
     #[inline(always)]
@@ -103,18 +477,32 @@ impl Lines {
     }
     
     #[inline(always)]
-    pub fn prev(&self) -> Option<&str> {
-        self.index.filter(|&i| i > 1).map(|i| &self.lines[i - 2].1[1..])
+    pub fn curr(&self) -> Option<&str> {
+        self.relative(0)
     }
     
+    // The line before (negative) or after (positive) the current line, the first line is after the current line before it starts
     #[inline(always)]
-    pub fn curr(&self) -> Option<&str> {
-        self.index.filter(|&i| i > 0).map(|i| &self.lines[i - 1].1[1..])
+    pub fn relative(&self, offset: isize) -> Option<&str> {
+        let i = (self.index? as isize - 1).checked_add(offset)?;
+        
+        usize::try_from(i).ok().and_then(|i| self.lines.get(i)).map(|line| line.text.as_str())
     }
     
+    // The part of the current line already sung, only for lines with word timing, which the console does not fill
+    #[cfg(any(feature = "x11-lyrics", feature = "wayland-lyrics", feature = "windows-lyrics"))]
     #[inline(always)]
-    pub fn next(&self) -> Option<&str> {
-        self.index.filter(|&i| i < self.lines.len()).map(|i| &self.lines[i].1[1..])
+    pub fn sung(&self) -> Option<&str> {
+        self.index.filter(|&i| i > 0).map(|i| &self.lines[i - 1]).filter(|line| line.words.len() > 1).map(|line| {
+            &line.text[..line.words.get(self.sung).map_or(line.text.len(), |&(_, j)| j)]
+        })
+    }
+    
+    // Only the x11 backend needs a monospace font for word timing
+    #[cfg(feature = "x11-lyrics")]
+    #[inline(always)]
+    pub fn has_word_timing(&self) -> bool {
+        self.lines.iter().any(|line| line.words.len() > 1)
     }
 }
 
@@ -125,183 +513,939 @@ macro_rules! error_enum {
             $($var($e),)*
         }
         
-        impl std::fmt::Display for $name {
-            #[inline(always)]
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                #[allow(unused_imports)]
-                use $name::*;
+        impl std::fmt::Display for $name {
+            #[inline(always)]
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                #[allow(unused_imports)]
+                use $name::*;
+                
+                match self {
+                    $($var(e) => std::fmt::Display::fmt(&e, f),)*
+                }
+            }
+        }
+        
+        $(
+            impl From<$e> for $name {
+                #[inline(always)]
+                fn from(value: $e) -> Self {
+                    $name::$var(value)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "x11-lyrics")]
+mod xosd {
+    use std::process::Command as Process;
+    
+    use super::*;
+    use xosd_rs::*;
+    
+    #[derive(Clone, Copy)]
+    struct Monitor {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    }
+    
+    // Active monitors from xrandr with the primary one first, which is where xosd places its windows,
+    // e.g. " 0: +*DP-1 1920/527x1080/296+0+0  DP-1"
+    fn monitors() -> Vec<Monitor> {
+        let Ok(output) = Process::new("xrandr").arg("--listactivemonitors").output() else { return Vec::new() };
+        
+        let parse = |line: &str| {
+            let mut words = line.split_whitespace().skip(1);
+            let primary = words.next()?.contains('*');
+            let (width, rest) = words.next()?.split_once('x')?;
+            let mut rest = rest.split('+');
+            let height = rest.next()?;
+            let number = |s: &str| s.split('/').next()?.parse().ok();
+            
+            Some((primary, Monitor {
+                x: number(rest.next()?)?,
+                y: number(rest.next()?)?,
+                width: number(width)?,
+                height: number(height)?,
+            }))
+        };
+        
+        let mut monitors: Vec<_> = String::from_utf8_lossy(&output.stdout).lines().skip(1).filter_map(parse).collect();
+        
+        monitors.sort_by_key(|&(primary, _)| !primary);
+        
+        monitors.into_iter().map(|(_, monitor)| monitor).collect()
+    }
+    
+    #[inline(always)]
+    fn font_pattern(family: &str, size: i32, monospace: bool) -> String {
+        format!("-*-{}-*-r-*-*-{}-*-*-*-{}-*-*-*", if family.is_empty() { "*" } else { family }, size, if monospace { "m" } else { "*" })
+    }
+    
+    #[inline(always)]
+    fn new_xosd(color: &str, size: i32, style: &LyricsStyle, h: HorizontalAlign, v: VerticalAlign, ho: i32, vo: i32) -> Result<Xosd> {
+        let mut xosd = Xosd::new(1)?;
+        
+        xosd.set_color(color)?;
+        xosd.set_outline_offset(style.outline)?;
+        xosd.set_outline_color(&style.outline_color)?;
+        xosd.set_shadow_offset(style.shadow)?;
+        xosd.set_shadow_color(&style.shadow_color)?;
+        set_monospace(&mut xosd, &style.font, size, false)?;
+        xosd.set_horizontal_align(h)?;
+        xosd.set_vertical_align(v)?;
+        xosd.set_horizontal_offset(ho)?;
+        xosd.set_vertical_offset(vo)?;
+        
+        Ok(xosd)
+    }
+    
+    type Rows = Vec<Xosd>;
+    
+    // Offsets of the lines before the current line (the furthest first), the current line,
+    // the rows of translations under it and the lines after it (the nearest first)
+    #[inline(always)]
+    fn get_offsets(v: VerticalAlign, small: i32, big: i32, before: i32, translations: i32, after: i32) -> (Vec<i32>, i32, Vec<i32>, Vec<i32>) {
+        let (b, t, a) = (0..before, 0..translations, 0..after);
+        
+        match v {
+            VerticalAlign::Top => (
+                b.map(|j| j * small).collect(),
+                before * small,
+                t.map(|i| before * small + big + i * small).collect(),
+                a.map(|j| before * small + big + translations * small + j * small).collect(),
+            ),
+            VerticalAlign::Center => (
+                b.map(|j| (small + big) / 2 + (before - 1 - j) * small).collect(),
+                0,
+                t.map(|i| -(small + big) / 2 - i * small).collect(),
+                a.map(|j| -(small + big) / 2 - translations * small - j * small).collect(),
+            ),
+            VerticalAlign::Bottom => (
+                b.map(|j| (after + translations) * small + big + (before - 1 - j) * small).collect(),
+                (after + translations) * small,
+                t.map(|i| (after + translations - 1 - i) * small).collect(),
+                a.map(|j| (after - 1 - j) * small).collect(),
+            ),
+        }
+    }
+    
+    // Windows of the lines before, the current line, the translations and the lines after
+    fn new_lines(layout: &LyricsLayout, monitors: &[Monitor], translations: usize) -> Result<(Rows, Xosd, Rows, Rows)> {
+        let style = &layout.style;
+        let (v, h, ho, vo) = get_xosd_align_margin(layout, monitors);
+        
+        let (ob, oc, ot, oa) = get_offsets(v, style.small_size, style.size, layout.lines_before as i32, translations as i32, layout.lines_after as i32);
+        let small = |color, offsets: Vec<i32>| offsets.into_iter().map(|o| new_xosd(color, style.small_size, style, h, v, ho, vo + o)).collect::<Result<Vec<_>>>();
+        
+        Ok((
+            small(&style.context_color, ob)?,
+            new_xosd(&style.color, style.size, style, h, v, ho, vo + oc)?,
+            small(&style.translation_color, ot)?,
+            small(&style.context_color, oa)?,
+        ))
+    }
+    
+    // The sung part of a karaoke line is drawn over the current line
+    fn new_fill(layout: &LyricsLayout, monitors: &[Monitor], translations: usize) -> Result<Xosd> {
+        let style = &layout.style;
+        let (v, h, ho, vo) = get_xosd_align_margin(layout, monitors);
+        
+        let (_, o, _, _) = get_offsets(v, style.small_size, style.size, layout.lines_before as i32, translations as i32, layout.lines_after as i32);
+        
+        new_xosd(&style.sung_color, style.size, style, h, v, ho, vo + o)
+    }
+    
+    // Karaoke lines use a monospace font so that the sung part padded with spaces lines up with the whole line,
+    // any monospace font is used if the font family has none
+    #[inline(always)]
+    fn set_monospace(xosd: &mut Xosd, family: &str, size: i32, monospace: bool) -> Result<()> {
+        if monospace && [family, ""].into_iter().any(|family| xosd.set_font(font_pattern(family, size, true)).is_ok()) {
+            return Ok(());
+        }
+        
+        xosd.set_font(font_pattern(family, size, false))?;
+        
+        Ok(())
+    }
+    
+    #[inline(always)]
+    fn pad(sung: &str, line: &str) -> String {
+        let wide = |c: char| matches!(c, '\u{1100}'..='\u{115F}' | '\u{2E80}'..='\u{A4CF}' | '\u{AC00}'..='\u{D7A3}' | '\u{F900}'..='\u{FAFF}' | '\u{FE30}'..='\u{FE4F}' | '\u{FF00}'..='\u{FF60}' | '\u{FFE0}'..='\u{FFE6}');
+        let width: usize = line[sung.len()..].chars().map(|c| if wide(c) { 2 } else { 1 }).sum();
+        
+        format!("{}{}", sung, " ".repeat(width))
+    }
+    
+    #[inline(always)]
+    fn show(xosd: &mut Xosd, string: Option<&str>) -> Result<()> {
+        if let Some(string) = string {
+            xosd.display(0, Command::String(string.to_string()))?;
+        }
+        else if xosd.onscreen()? {
+            xosd.hide()?;
+        }
+        
+        Ok(())
+    }
+    
+    // The offsets are relative to the edges of the first monitor, so the other monitors are reached by
+    // adding the distance between the same edges of the two monitors
+    #[inline(always)]
+    fn get_xosd_align_margin(layout: &LyricsLayout, monitors: &[Monitor]) -> (VerticalAlign, HorizontalAlign, i32, i32) {
+        use LyricsPosition::*;
+        use HorizontalAlign::{Left, Right, Center as HCenter};
+        use VerticalAlign::{Top, Bottom, Center as VCenter};
+        
+        let (mut v, mut h, mut ho, mut vo) = match layout.position {
+            TopLeft => (Top, Left, layout.margin, layout.margin),
+            TopCenter => (Top, HCenter, 0, layout.margin),
+            TopRight => (Top, Right, layout.margin, layout.margin),
+            CenterLeft => (VCenter, Left, layout.margin, 0),
+            Center => (VCenter, HCenter, 0, 0),
+            CenterRight => (VCenter, Right, layout.margin, 0),
+            BottomLeft => (Bottom, Left, layout.margin, layout.margin),
+            BottomCenter => (Bottom, HCenter, 0, layout.margin),
+            BottomRight => (Bottom, Right, layout.margin, layout.margin),
+        };
+        
+        if let Some(x) = layout.x {
+            (h, ho) = (Left, x);
+        }
+        
+        if let Some(y) = layout.y {
+            (v, vo) = (Top, y);
+        }
+        
+        if let (Some(first), Some(m)) = (monitors.first(), monitors.get(layout.monitor)) {
+            ho += match h {
+                Left => m.x - first.x,
+                HCenter => (m.x + m.width / 2) - (first.x + first.width / 2),
+                Right => (first.x + first.width) - (m.x + m.width),
+            };
+            
+            // a positive offset moves a centered window up
+            vo += match v {
+                Top => m.y - first.y,
+                VCenter => (first.y + first.height / 2) - (m.y + m.height / 2),
+                Bottom => (first.y + first.height) - (m.y + m.height),
+            };
+        }
+        
+        (v, h, ho, vo)
+    }
+    
+    pub struct XosdLyrics {
+        lines: Lines,
+        layout: LyricsLayout,
+        before: Vec<Xosd>, // the furthest first
+        curr: Xosd,
+        after: Vec<Xosd>, // the nearest first
+        fill: Xosd,
+        translations: Vec<Xosd>,
+        monitors: Vec<Monitor>,
+    }
+    
+    impl XosdLyrics {
+        #[inline(always)]
+        fn update_text(&mut self) -> Result<()> {
+            if self.layout.visible {
+                let before = self.before.len() as isize;
+                
+                for (j, xosd) in self.before.iter_mut().enumerate() {
+                    show(xosd, self.lines.relative(j as isize - before))?;
+                }
+                
+                show(&mut self.curr, self.lines.curr())?;
+                
+                for (j, xosd) in self.after.iter_mut().enumerate() {
+                    show(xosd, self.lines.relative(j as isize + 1))?;
+                }
+                
+                show(&mut self.fill, self.lines.sung().zip(self.lines.curr()).map(|(sung, line)| pad(sung, line)).as_deref())?;
+                
+                for (i, xosd) in self.translations.iter_mut().enumerate() {
+                    show(xosd, self.lines.translation(i))?;
+                }
+                
+                return Ok(());
+            }
+            
+            show(&mut self.curr, None)?;
+            show(&mut self.fill, None)?;
+            
+            for xosd in self.before.iter_mut().chain(&mut self.after).chain(&mut self.translations) {
+                show(xosd, None)?;
+            }
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn update_font(&mut self) -> Result<()> {
+            let monospace = self.lines.has_word_timing();
+            let style = &self.layout.style;
+            
+            set_monospace(&mut self.curr, &style.font, style.size, monospace)?;
+            set_monospace(&mut self.fill, &style.font, style.size, monospace)?;
+            
+            Ok(())
+        }
+        
+        // Recreates the windows for a new layout or number of translations
+        #[inline(always)]
+        fn rebuild(&mut self) -> Result<()> {
+            let count = self.lines.translation_count();
+            let (before, curr, translations, after) = new_lines(&self.layout, &self.monitors, count)?;
+            
+            self.before = before;
+            self.curr = curr;
+            self.after = after;
+            self.translations = translations;
+            self.fill = new_fill(&self.layout, &self.monitors, count)?;
+            
+            self.update_font()
+        }
+    }
+    
+    impl LyricsTrait for XosdLyrics {
+        type Error = Error;
+        
+        #[inline(always)]
+        fn new(layout: LyricsLayout) -> Result<Self> {
+            let monitors = monitors();
+            let (before, curr, translations, after) = new_lines(&layout, &monitors, 0)?;
+            let fill = new_fill(&layout, &monitors, 0)?;
+            
+            Ok(Self {
+                lines: Lines::new(),
+                layout,
+                before,
+                curr,
+                after,
+                fill,
+                translations,
+                monitors,
+            })
+        }
+        
+        #[inline(always)]
+        fn set_layout(&mut self, layout: &LyricsLayout) -> Result<()> {
+            if self.layout.visible != layout.visible {
+                self.layout.visible = layout.visible;
+                
+                self.update_text()?;
+            }
+            
+            if &self.layout != layout {
+                self.layout = layout.clone();
+                self.rebuild()?;
+                self.update_text()?;
+            }
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn set_lyrics(&mut self, lyrics: Vec<(Duration, String)>) -> Result<()> {
+            self.lines.set(lyrics);
+            
+            if self.translations.is_empty() {
+                self.update_font()?;
+            }
+            else {
+                self.rebuild()?;
+            }
+            
+            self.update_text()?;
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn set_translations(&mut self, translations: Vec<Vec<(Duration, String)>>) -> Result<()> {
+            self.lines.set_translations(translations);
+            
+            if self.translations.len() != self.lines.translation_count() {
+                self.rebuild()?;
+            }
+            
+            self.update_text()?;
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn clear(&mut self) -> Result<()> {
+            self.lines.clear();
+            self.update_text()?;
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn update(&mut self, time: Duration) -> Result<()> {
+            if self.lines.update(time) {
+                self.update_text()?;
+            }
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn monitor_count(&self) -> usize {
+            self.monitors.len().max(1)
+        }
+        
+        // xrandr is only run here and when created as monitors are rarely plugged in or out
+        #[inline(always)]
+        fn refresh_monitors(&mut self) {
+            self.monitors = monitors();
+        }
+    }
+}
+
+#[cfg(feature = "console")]
+mod console {
+    use super::*;
+    
+    // Prints each line when it starts, the lines before it are still in the terminal
+    pub struct ConsoleLyrics {
+        lines: Lines,
+        visible: bool,
+        printed: Option<usize>,
+    }
+    
+    impl ConsoleLyrics {
+        #[inline(always)]
+        fn print(&mut self) {
+            if self.visible && self.lines.current() != self.printed {
+                if let Some(line) = self.lines.curr() {
+                    println!("LYRICS: {}", line);
+                    
+                    for i in 0..self.lines.translation_count() {
+                        if let Some(translation) = self.lines.translation(i) {
+                            println!("LYRICS:     {}", translation);
+                        }
+                    }
+                }
+            }
+            
+            self.printed = self.lines.current();
+        }
+    }
+    
+    impl LyricsTrait for ConsoleLyrics {
+        type Error = std::convert::Infallible;
+        
+        #[inline(always)]
+        fn new(layout: LyricsLayout) -> Result<Self, Self::Error> {
+            Ok(Self {
+                lines: Lines::new(),
+                visible: layout.visible,
+                printed: None,
+            })
+        }
+        
+        #[inline(always)]
+        fn set_lyrics(&mut self, lyrics: Vec<(Duration, String)>) -> Result<(), Self::Error> {
+            self.lines.set(lyrics);
+            self.printed = None;
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn set_translations(&mut self, translations: Vec<Vec<(Duration, String)>>) -> Result<(), Self::Error> {
+            self.lines.set_translations(translations);
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn clear(&mut self) -> Result<(), Self::Error> {
+            self.lines.clear();
+            self.printed = None;
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn update(&mut self, time: Duration) -> Result<(), Self::Error> {
+            if self.lines.update(time) {
+                self.print();
+            }
+            
+            Ok(())
+        }
+        
+        #[inline(always)]
+        fn set_layout(&mut self, layout: &LyricsLayout) -> Result<(), Self::Error> {
+            self.visible = layout.visible;
+            
+            Ok(())
+        }
+    }
+}
+
+#[cfg(feature = "wayland-lyrics")]
+mod wayland {
+    use std::{fs::read, io::ErrorKind, process::Command};
+    
+    use super::*;
+    use crate::error;
+    use fontdue::{Font, FontSettings};
+    use smithay_client_toolkit::{
+        compositor::{CompositorHandler, CompositorState, Region},
+        delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm,
+        output::{OutputHandler, OutputState},
+        reexports::client::{
+            globals::{registry_queue_init, BindError, GlobalError},
+            protocol::{wl_output, wl_shm, wl_surface},
+            backend::WaylandError,
+            ConnectError, Connection, DispatchError, EventQueue, QueueHandle,
+        },
+        registry::{ProvidesRegistryState, RegistryState},
+        registry_handlers,
+        shell::{
+            wlr_layer::{Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface, LayerSurfaceConfigure},
+            WaylandSurface,
+        },
+        shm::{slot::{ActivateSlotError, Buffer, CreateBufferError, SlotPool}, CreatePoolError, Shm, ShmHandler},
+    };
+    
+    error_enum!(Error
+        Connect ConnectError
+        Global GlobalError
+        Region smithay_client_toolkit::error::GlobalError
+        Bind BindError
+        Pool CreatePoolError
+        Buffer CreateBufferError
+        Attach ActivateSlotError
+        Dispatch DispatchError
+        Wayland WaylandError
+        Io std::io::Error
+        Font &'static str
+    );
+    
+    type Result<T> = std::result::Result<T, Error>;
+    
+    // Any font fontconfig matches with the family, the default sans-serif font without one
+    fn load_font(family: &str) -> Result<Font> {
+        let output = Command::new("fc-match").arg("--format=%{file}").arg(if family.is_empty() { "sans-serif" } else { family }).output()?;
+        let path = String::from_utf8_lossy(&output.stdout).into_owned();
+        
+        if path.is_empty() {
+            return Err(Error::Font("No font found by fc-match"));
+        }
+        
+        Ok(Font::from_bytes(read(path)?, FontSettings::default())?)
+    }
+    
+    #[inline(always)]
+    fn argb(color: &str) -> u32 {
+        0xFF000000 | rgb(color).unwrap_or(0xFFFFFF)
+    }
+    
+    #[inline(always)]
+    fn line_height(font: &Font, size: f32) -> i32 {
+        font.horizontal_line_metrics(size).map_or(size * 1.2, |metrics| metrics.new_line_size).ceil() as i32
+    }
+    
+    #[inline(always)]
+    fn text_width(font: &Font, size: f32, s: &str) -> i32 {
+        s.chars().map(|c| font.metrics(c, size).advance_width).sum::<f32>().ceil() as i32
+    }
+    
+    // The canvas is premultiplied ARGB in little endian
+    #[inline(always)]
+    fn blend(pixel: &mut [u8], color: u32, coverage: u8) {
+        let a = coverage as u32;
+        let [b, g, r, _] = color.to_le_bytes();
+        
+        for (dst, src) in pixel.iter_mut().zip([b, g, r, 255]) {
+            *dst = ((src as u32 * a + *dst as u32 * (255 - a)) / 255) as u8;
+        }
+    }
+    
+    // A row of text with its baseline at y, the first `sung` bytes are drawn in the sung color
+    struct Row {
+        text: String,
+        size: f32,
+        color: u32,
+        sung: usize,
+        sung_color: u32,
+    }
+    
+    struct Canvas<'a> {
+        data: &'a mut [u8],
+        width: i32,
+        height: i32,
+    }
+    
+    impl Canvas<'_> {
+        fn draw(&mut self, font: &Font, row: &Row, x: i32, y: i32, color: Option<u32>) {
+            let mut pen = x as f32;
+            
+            for (i, c) in row.text.char_indices() {
+                let (metrics, bitmap) = font.rasterize(c, row.size);
+                let color = color.unwrap_or(if i < row.sung { row.sung_color } else { row.color });
+                let left = pen.round() as i32 + metrics.xmin;
+                let top = y - metrics.height as i32 - metrics.ymin;
+                
+                for (j, &coverage) in bitmap.iter().enumerate().filter(|&(_, &coverage)| coverage > 0) {
+                    let (px, py) = (left + (j % metrics.width) as i32, top + (j / metrics.width) as i32);
+                    
+                    if (0..self.width).contains(&px) && (0..self.height).contains(&py) {
+                        let k = ((py * self.width + px) * 4) as usize;
+                        
+                        blend(&mut self.data[k..k + 4], color, coverage);
+                    }
+                }
+                
+                pen += metrics.advance_width;
+            }
+        }
+    }
+    
+    struct State {
+        registry_state: RegistryState,
+        output_state: OutputState,
+        compositor: CompositorState,
+        layer_shell: LayerShell,
+        shm: Shm,
+        pool: SlotPool,
+        buffer: Option<Buffer>,
+        layer: Option<LayerSurface>,
+        qh: QueueHandle<State>,
+        font: Font,
+        lines: Lines,
+        layout: LyricsLayout,
+        width: u32,
+        height: u32,
+        scale: i32, // of the output, the buffer is drawn that many times larger than the surface
+        configured: bool,
+    }
+    
+    impl State {
+        // The surface spans the width of the output so that its height is the only thing that changes with the layout
+        fn rebuild(&mut self) -> Result<()> {
+            use LyricsPosition::*;
+            
+            let style = &self.layout.style;
+            let (small, big) = (line_height(&self.font, style.small_size as f32), line_height(&self.font, style.size as f32));
+            let rows = (self.layout.lines_before + self.layout.lines_after + self.lines.translation_count()) as i32;
+            let pad = style.outline.max(0) + style.shadow.max(0);
+            
+            self.height = (rows * small + big + 2 * pad) as u32;
+            self.configured = false;
+            
+            let anchor = Anchor::LEFT | Anchor::RIGHT | match self.layout.position {
+                _ if self.layout.y.is_some() => Anchor::TOP,
+                TopLeft | TopCenter | TopRight => Anchor::TOP,
+                CenterLeft | Center | CenterRight => Anchor::empty(),
+                BottomLeft | BottomCenter | BottomRight => Anchor::BOTTOM,
+            };
+            
+            let margin = self.layout.margin;
+            let (top, side) = (self.layout.y.unwrap_or(margin), if self.layout.x.is_some() { 0 } else { margin });
+            
+            let layer = self.layer.take().unwrap_or_else(|| {
+                let surface = self.compositor.create_surface(&self.qh);
+                let output = self.output_state.outputs().nth(self.layout.monitor).or_else(|| self.output_state.outputs().next());
+                
+                self.layer_shell.create_layer_surface(&self.qh, surface, Layer::Overlay, Some("lyrics"), output.as_ref())
+            });
+            
+            // an empty input region lets clicks through
+            let region = Region::new(&self.compositor)?;
+            
+            layer.wl_surface().set_input_region(Some(region.wl_region()));
+            layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+            layer.set_anchor(anchor);
+            layer.set_margin(top, side, margin, side);
+            layer.set_size(0, self.height);
+            layer.commit();
+            
+            self.layer.replace(layer);
+            
+            Ok(())
+        }
+        
+        fn rows(&self) -> Vec<Row> {
+            let style = &self.layout.style;
+            let row = |text: Option<&str>, size: i32, color: &str| Row {
+                text: text.unwrap_or_default().to_string(),
+                size: (size * self.scale) as f32,
+                color: argb(color),
+                sung: 0,
+                sung_color: argb(&style.sung_color),
+            };
+            
+            let before = self.layout.lines_before as isize;
+            let mut rows: Vec<_> = (0..before).map(|j| row(self.lines.relative(j - before), style.small_size, &style.context_color)).collect();
+            
+            rows.push(Row { sung: self.lines.sung().map_or(0, str::len), ..row(self.lines.curr(), style.size, &style.color) });
+            rows.extend((0..self.lines.translation_count()).map(|i| row(self.lines.translation(i), style.small_size, &style.translation_color)));
+            rows.extend((1..=self.layout.lines_after as isize).map(|j| row(self.lines.relative(j), style.small_size, &style.context_color)));
+            
+            rows
+        }
+        
+        fn draw(&mut self) -> Result<()> {
+            let Some(layer) = &self.layer else { return self.rebuild() };
+            
+            if !self.configured || self.width == 0 {
+                return Ok(());
+            }
+            
+            use LyricsPosition::*;
+            
+            // everything is drawn in the pixels of the buffer
+            let s = self.scale;
+            let (width, height) = (self.width as i32 * s, self.height as i32 * s);
+            let rows = if self.layout.visible { self.rows() } else { Vec::new() };
+            let style = &self.layout.style;
+            let (shadow, outline) = (style.shadow * s, style.outline * s);
+            let pad = outline.max(0) + shadow.max(0);
+            let (buffer, data) = self.pool.create_buffer(width, height, width * 4, wl_shm::Format::Argb8888)?;
+            let mut canvas = Canvas { data, width, height };
+            
+            canvas.data.fill(0);
+            
+            let mut top = pad;
+            
+            for row in &rows {
+                let h = line_height(&self.font, row.size);
+                let y = top + self.font.horizontal_line_metrics(row.size).map_or(row.size, |metrics| metrics.ascent).round() as i32;
+                let w = text_width(&self.font, row.size, &row.text);
+                let x = self.layout.x.map(|x| x * s).unwrap_or(match self.layout.position {
+                    TopLeft | CenterLeft | BottomLeft => pad,
+                    TopCenter | Center | BottomCenter => (width - w) / 2,
+                    TopRight | CenterRight | BottomRight => width - w - pad,
+                });
                 
-                match self {
-                    $($var(e) => std::fmt::Display::fmt(&e, f),)*
+                if shadow > 0 {
+                    canvas.draw(&self.font, row, x + shadow, y + shadow, Some(argb(&style.shadow_color)));
+                }
+                
+                if outline > 0 {
+                    let o = outline;
+                    
+                    for (dx, dy) in [(-o, -o), (0, -o), (o, -o), (-o, 0), (o, 0), (-o, o), (0, o), (o, o)] {
+                        canvas.draw(&self.font, row, x + dx, y + dy, Some(argb(&style.outline_color)));
+                    }
                 }
+                
+                canvas.draw(&self.font, row, x, y, None);
+                
+                top += h;
             }
+            
+            layer.wl_surface().set_buffer_scale(s);
+            layer.wl_surface().damage_buffer(0, 0, width, height);
+            buffer.attach_to(layer.wl_surface())?;
+            layer.commit();
+            
+            // the previous buffer is released by the compositor once the new one is shown
+            self.buffer.replace(buffer);
+            
+            Ok(())
         }
-        
-        $(
-            impl From<$e> for $name {
-                #[inline(always)]
-                fn from(value: $e) -> Self {
-                    $name::$var(value)
+    }
+    
+    impl CompositorHandler for State {
+        fn scale_factor_changed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _surface: &wl_surface::WlSurface, new_factor: i32) {
+            if self.scale != new_factor {
+                self.scale = new_factor;
+                
+                if let Err(e) = self.draw() {
+                    error!(e, "Failed to draw lyrics");
                 }
             }
-        )*
-    };
-}
-
-#[cfg(feature = "x11-lyrics")]
-mod xosd {
-    use super::*;
-    use xosd_rs::*;
-    
-    #[inline(always)]
-    fn new_xosd(color: &str, size: u8, osize: i32, h: HorizontalAlign, v: VerticalAlign, ho: i32, vo: i32) -> Result<Xosd> {
-        let mut xosd = Xosd::new(1)?;
+        }
         
-        xosd.set_color(color)?;
-        xosd.set_outline_offset(osize)?;
-        xosd.set_font(format!("-*-*-*-r-*-*-{}-*-*-*-*-*-*-*", size))?;
-        xosd.set_horizontal_align(h)?;
-        xosd.set_vertical_align(v)?;
-        xosd.set_horizontal_offset(ho)?;
-        xosd.set_vertical_offset(vo)?;
+        fn transform_changed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _surface: &wl_surface::WlSurface, _new_transform: wl_output::Transform) {}
         
-        Ok(xosd)
-    }
-    
-    fn new_three(small_color: &str, big_color: &str, small: i32, big: i32, osize: i32, layout: LyricsLayout) -> Result<(Xosd, Xosd, Xosd)> {
-        let (v, h, ho, vo) = get_xosd_align_margin(layout);
+        fn frame(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _surface: &wl_surface::WlSurface, _time: u32) {}
         
-        let (o1, o2, o3) = match v {
-            VerticalAlign::Top => (0, small, small + big),
-            VerticalAlign::Center => ((small + big) / 2, 0, -(small + big) / 2),
-            VerticalAlign::Bottom => (small + big, small, 0),
-        };
+        fn surface_enter(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _surface: &wl_surface::WlSurface, _output: &wl_output::WlOutput) {}
         
-        Ok((
-            new_xosd(small_color, small as u8, osize, h, v, ho, vo + o1)?,
-            new_xosd(big_color, big as u8, osize, h, v, ho, vo + o2)?,
-            new_xosd(small_color, small as u8, osize, h, v, ho, vo + o3)?,
-        ))
+        fn surface_leave(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _surface: &wl_surface::WlSurface, _output: &wl_output::WlOutput) {}
     }
     
-    #[inline(always)]
-    fn show(xosd: &mut Xosd, string: Option<&str>) -> Result<()> {
-        if let Some(string) = string {
-            xosd.display(0, Command::String(string.to_string()))?;
-        }
-        else if xosd.onscreen()? {
-            xosd.hide()?;
+    impl OutputHandler for State {
+        fn output_state(&mut self) -> &mut OutputState {
+            &mut self.output_state
         }
         
-        Ok(())
+        fn new_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: wl_output::WlOutput) {}
+        
+        fn update_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: wl_output::WlOutput) {}
+        
+        fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: wl_output::WlOutput) {}
     }
     
-    #[inline(always)]
-    fn get_xosd_align_margin(layout: LyricsLayout) -> (VerticalAlign, HorizontalAlign, i32, i32) {
-        use LyricsPosition::*;
-        use HorizontalAlign::{Left, Right, Center as HCenter};
-        use VerticalAlign::{Top, Bottom, Center as VCenter};
+    impl LayerShellHandler for State {
+        // e.g. the output is gone, the surface is created again the next time the lyrics change
+        fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _layer: &LayerSurface) {
+            self.layer.take();
+            self.configured = false;
+        }
         
-        match layout.position {
-            TopLeft => (Top, Left, layout.margin, layout.margin),
-            TopCenter => (Top, HCenter, 0, layout.margin),
-            TopRight => (Top, Right, layout.margin, layout.margin),
-            CenterLeft => (VCenter, Left, layout.margin, 0),
-            Center => (VCenter, HCenter, 0, 0),
-            CenterRight => (VCenter, Right, layout.margin, 0),
-            BottomLeft => (Bottom, Left, layout.margin, layout.margin),
-            BottomCenter => (Bottom, HCenter, 0, layout.margin),
-            BottomRight => (Bottom, Right, layout.margin, layout.margin),
+        fn configure(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _layer: &LayerSurface, configure: LayerSurfaceConfigure, _serial: u32) {
+            self.width = configure.new_size.0;
+            self.configured = true;
+            
+            if let Err(e) = self.draw() {
+                error!(e, "Failed to draw lyrics");
+            }
         }
     }
     
-    pub struct XosdLyrics {
-        lines: Lines,
-        layout: LyricsLayout,
-        prev: Xosd,
-        curr: Xosd,
-        next: Xosd,
+    impl ShmHandler for State {
+        fn shm_state(&mut self) -> &mut Shm {
+            &mut self.shm
+        }
     }
     
-    impl XosdLyrics {
-        #[inline(always)]
-        fn update_text(&mut self) -> Result<()> {
-            if self.layout.visible {
-                show(&mut self.prev, self.lines.prev())?;
-                show(&mut self.curr, self.lines.curr())?;
-                show(&mut self.next, self.lines.next())?;
-                
-                return Ok(());
-            }
-            
-            show(&mut self.prev, None)?;
-            show(&mut self.curr, None)?;
-            show(&mut self.next, None)?;
-            
-            Ok(())
+    impl ProvidesRegistryState for State {
+        fn registry(&mut self) -> &mut RegistryState {
+            &mut self.registry_state
         }
+        
+        registry_handlers![OutputState];
     }
     
-    impl LyricsTrait for XosdLyrics {
+    delegate_compositor!(State);
+    delegate_output!(State);
+    delegate_shm!(State);
+    delegate_layer!(State);
+    delegate_registry!(State);
+    
+    pub struct WaylandLyrics {
+        _conn: Connection,
+        queue: EventQueue<State>,
+        state: State,
+    }
+    
+    impl LyricsTrait for WaylandLyrics {
         type Error = Error;
         
-        #[inline(always)]
         fn new(layout: LyricsLayout) -> Result<Self> {
-            let (prev, curr, next) = new_three("dark gray", "white", 24, 32, 2, layout)?;
+            let conn = Connection::connect_to_env()?;
+            let (globals, queue) = registry_queue_init(&conn)?;
+            let qh = queue.handle();
+            let shm = Shm::bind(&globals, &qh)?;
             
-            Ok(Self {
+            let mut state = State {
+                registry_state: RegistryState::new(&globals),
+                output_state: OutputState::new(&globals, &qh),
+                compositor: CompositorState::bind(&globals, &qh)?,
+                layer_shell: LayerShell::bind(&globals, &qh)?,
+                pool: SlotPool::new(4096, &shm)?,
+                shm,
+                buffer: None,
+                layer: None,
+                qh,
+                font: load_font(&layout.style.font)?,
                 lines: Lines::new(),
                 layout,
-                prev,
-                curr,
-                next,
-            })
+                width: 0,
+                height: 0,
+                scale: 1,
+                configured: false,
+            };
+            
+            state.rebuild()?;
+            
+            Ok(Self { _conn: conn, queue, state })
         }
         
         #[inline(always)]
-        fn set_layout(&mut self, layout: LyricsLayout) -> Result<()> {
-            if self.layout.visible != layout.visible {
-                self.layout.visible = layout.visible;
-                
-                self.update_text()?;
-            }
-            
-            if self.layout != layout {
-                let (prev, curr, next) = new_three("dark gray", "white", 24, 32, 2, layout)?;
+        fn set_layout(&mut self, layout: &LyricsLayout) -> Result<()> {
+            if &self.state.layout != layout {
+                if self.state.layout.style.font != layout.style.font {
+                    self.state.font = load_font(&layout.style.font)?;
+                }
                 
-                self.prev = prev;
-                self.curr = curr;
-                self.next = next;
+                // the output of a layer surface is only chosen when it is created
+                if self.state.layout.monitor != layout.monitor {
+                    self.state.layer.take();
+                }
                 
-                self.layout = layout;
-                self.update_text()?;
+                self.state.layout = layout.clone();
+                self.state.rebuild()?;
             }
             
             Ok(())
         }
         
+        #[inline(always)]
+        fn monitor_count(&self) -> usize {
+            self.state.output_state.outputs().count().max(1)
+        }
+        
         #[inline(always)]
         fn set_lyrics(&mut self, lyrics: Vec<(Duration, String)>) -> Result<()> {
-            self.lines.set(lyrics);
-            self.update_text()?;
+            let count = self.state.lines.translation_count();
             
-            Ok(())
+            self.state.lines.set(lyrics);
+            
+            if count == 0 {
+                self.state.draw()
+            }
+            else {
+                self.state.rebuild()
+            }
         }
         
         #[inline(always)]
-        fn clear(&mut self) -> Result<()> {
-            self.lines.clear();
-            self.update_text()?;
+        fn set_translations(&mut self, translations: Vec<Vec<(Duration, String)>>) -> Result<()> {
+            let count = self.state.lines.translation_count();
             
-            Ok(())
+            self.state.lines.set_translations(translations);
+            
+            if count == self.state.lines.translation_count() {
+                self.state.draw()
+            }
+            else {
+                self.state.rebuild()
+            }
+        }
+        
+        #[inline(always)]
+        fn clear(&mut self) -> Result<()> {
+            self.state.lines.clear();
+            self.state.draw()
         }
         
         #[inline(always)]
         fn update(&mut self, time: Duration) -> Result<()> {
-            if self.lines.update(time) {
-                self.update_text()?;
+            if self.state.lines.update(time) {
+                self.state.draw()?;
+            }
+            
+            Ok(())
+        }
+        
+        // Handles the events of the compositor without blocking
+        fn refresh(&mut self) -> Result<()> {
+            self.queue.dispatch_pending(&mut self.state)?;
+            self.queue.flush()?;
+            
+            if let Some(guard) = self.queue.prepare_read() {
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e.into()),
+                }
             }
             
+            self.queue.dispatch_pending(&mut self.state)?;
+            
             Ok(())
         }
     }
@@ -370,7 +1514,17 @@ mod windows {
         }
         
         #[inline(always)]
-        pub fn set_font_size(hdc: HDC, size: i32, f: impl FnOnce()) {
+        pub fn text_width(hdc: HDC, s: &str) -> i32 {
+            let mut size = SIZE::default();
+            
+            unsafe { let _ = GetTextExtentPoint32W(hdc, &s.encode_utf16().collect::<Vec<_>>(), &mut size); }
+            
+            size.cx
+        }
+        
+        // An empty family keeps the default GUI font
+        #[inline(always)]
+        pub fn set_font(hdc: HDC, family: &str, size: i32, f: impl FnOnce()) {
             unsafe {
                 let hfont = GetStockObject(DEFAULT_GUI_FONT);
                 let logfont = &mut LOGFONTW::default();
@@ -379,6 +1533,14 @@ mod windows {
                 
                 logfont.lfHeight = size;
                 
+                if !family.is_empty() {
+                    logfont.lfFaceName = [0; 32];
+                    
+                    for (c, d) in family.encode_utf16().take(31).zip(logfont.lfFaceName.iter_mut()) {
+                        *d = c;
+                    }
+                }
+                
                 let font = CreateFontIndirectW(logfont);
                 let old = SelectObject(hdc, font);
                 
@@ -389,6 +1551,22 @@ mod windows {
             }
         }
         
+        unsafe extern "system" fn monitor_proc(_monitor: HMONITOR, _hdc: HDC, r: *mut RECT, l: LPARAM) -> BOOL {
+            (*(l.0 as *mut Vec<RECT>)).push(*r);
+            TRUE
+        }
+        
+        // Rects of the monitors in the virtual screen with the primary monitor, which is at the origin, first
+        pub fn monitors() -> Vec<RECT> {
+            let mut monitors: Vec<RECT> = Vec::new();
+            
+            unsafe { let _ = EnumDisplayMonitors(HDC::default(), None, Some(monitor_proc), LPARAM(&mut monitors as *mut _ as isize)); }
+            
+            monitors.sort_by_key(|r| (r.left, r.top) != (0, 0));
+            
+            monitors
+        }
+        
         pub struct PaintCallback<T>(PhantomData<T>);
 
         impl<T: Paint> WindowCallback<T> for PaintCallback<T> where PaintCallback<T>: WindowName {
@@ -512,6 +1690,11 @@ mod windows {
             pub fn get_hwnd(&self) -> HWND {
                 self.hwnd
             }
+            
+            #[inline(always)]
+            pub fn move_to(&self, r: RECT) -> Result<()> {
+                unsafe { SetWindowPos(self.hwnd, HWND_TOPMOST, r.left, r.top, r.right - r.left, r.bottom - r.top, SWP_NOACTIVATE) }
+            }
         }
     }
     
@@ -547,54 +1730,136 @@ mod windows {
         }
     }
     
+    // Black is the transparent color key of the window, so it is drawn as the closest visible color
+    #[inline(always)]
+    fn colorref(color: &str) -> COLORREF {
+        let rgb = rgb(color).unwrap_or(0xFFFFFF);
+        let bgr = (rgb & 0xFF) << 16 | (rgb & 0xFF00) | (rgb >> 16);
+        
+        COLORREF(bgr.max(0x010101))
+    }
+    
+    #[inline(always)]
+    fn offset_rect(r: RECT, dx: i32, dy: i32) -> RECT {
+        RECT { left: r.left + dx, top: r.top + dy, right: r.right + dx, bottom: r.bottom + dy }
+    }
+    
+    // The shadow and the outline are the text drawn again under it, moved by their width
+    #[inline(always)]
+    unsafe fn draw_styled(hdc: HDC, r: RECT, s: &str, dt: DRAW_TEXT_FORMAT, color: &str, style: &LyricsStyle) {
+        if style.shadow > 0 {
+            SetTextColor(hdc, colorref(&style.shadow_color));
+            draw_text(hdc, offset_rect(r, style.shadow, style.shadow), s, dt);
+        }
+        
+        if style.outline > 0 {
+            let o = style.outline;
+            
+            SetTextColor(hdc, colorref(&style.outline_color));
+            
+            for (dx, dy) in [(-o, -o), (0, -o), (o, -o), (-o, 0), (o, 0), (-o, o), (0, o), (o, o)] {
+                draw_text(hdc, offset_rect(r, dx, dy), s, dt);
+            }
+        }
+        
+        SetTextColor(hdc, colorref(color));
+        draw_text(hdc, r, s, dt);
+    }
+    
     impl Paint for LyricsArc {
         unsafe fn paint(_hwnd: ::windows::Win32::Foundation::HWND, hdc: ::windows::Win32::Graphics::Gdi::HDC, mut r: ::windows::Win32::Foundation::RECT, rw: &std::sync::RwLock<Self>) {
             match rw.try_write() {
                 Ok(mut arc) => {
                     let arc = &mut *arc;
-                    let dt = translate_position(arc.layout.position) | DT_SINGLELINE | DT_INTERNAL;
+                    let mut dt = translate_position(arc.layout.position) | DT_SINGLELINE | DT_INTERNAL;
                     
                     r.top += arc.layout.margin;
                     r.left += arc.layout.margin;
                     r.bottom -= arc.layout.margin;
                     r.right -= arc.layout.margin;
                     
-                    let big = 32;
-                    let small = 24;
+                    // an absolute position replaces the alignment and the margin on its axis
+                    if let Some(x) = arc.layout.x {
+                        dt = (dt & !(DT_CENTER | DT_RIGHT)) | DT_LEFT;
+                        r.left = x;
+                    }
+                    
+                    if let Some(y) = arc.layout.y {
+                        dt = (dt & !(DT_VCENTER | DT_BOTTOM)) | DT_TOP;
+                        r.top = y;
+                    }
+                    
+                    let style = &arc.layout.style;
+                    let big = style.size;
+                    let small = style.small_size;
+                    let rows = arc.lines.translation_count() as i32; // translations under the current line
+                    let before = arc.layout.lines_before as i32;
+                    let after = arc.layout.lines_after as i32;
                     
                     if arc.layout.visible {
-                        set_font_size(hdc, big, || {
+                        set_font(hdc, &style.font, big, || {
                             if let Some(s) = arc.lines.curr() {
-                                SetTextColor(hdc, COLORREF(0x00FFFFFF));
-                                
-                                draw_text(hdc, match dt {
-                                    _dt if dt & DT_TOP == DT_TOP => RECT { top: r.top + small, ..r },
+                                let rect = match dt {
+                                    _dt if dt & DT_TOP == DT_TOP => RECT { top: r.top + before * small, ..r },
                                     _dt if dt & DT_VCENTER == DT_VCENTER => r,
-                                    _dt if dt & DT_BOTTOM == DT_BOTTOM => RECT { bottom: r.bottom - small, ..r },
+                                    _dt if dt & DT_BOTTOM == DT_BOTTOM => RECT { bottom: r.bottom - (after + rows) * small, ..r },
                                     _ => unimplemented!(),
-                                }, s, dt);
+                                };
+                                
+                                draw_styled(hdc, rect, s, dt, &style.color, style);
+                                
+                                // the sung part of a karaoke line is drawn again over it, clipped to its width
+                                if let Some(sung) = arc.lines.sung() {
+                                    let width = text_width(hdc, s);
+                                    let left = match dt {
+                                        _dt if dt & DT_CENTER == DT_CENTER => (rect.left + rect.right - width) / 2,
+                                        _dt if dt & DT_RIGHT == DT_RIGHT => rect.right - width,
+                                        _ => rect.left,
+                                    };
+                                    
+                                    let saved = SaveDC(hdc);
+                                    
+                                    IntersectClipRect(hdc, left, rect.top, left + text_width(hdc, sung), rect.bottom);
+                                    draw_styled(hdc, rect, s, dt, &style.sung_color, style);
+                                    let _ = RestoreDC(hdc, saved);
+                                }
                             }
                         });
                         
-                        set_font_size(hdc, small, || {
-                            SetTextColor(hdc, COLORREF(0x00777777));
+                        set_font(hdc, &style.font, small, || {
+                            // d lines before the current line
+                            for d in 1..=before {
+                                if let Some(s) = arc.lines.relative(-d as isize) {
+                                    draw_styled(hdc, match dt {
+                                        _dt if dt & DT_TOP == DT_TOP => RECT { top: r.top + (before - d) * small, ..r },
+                                        _dt if dt & DT_VCENTER == DT_VCENTER => RECT { bottom: r.bottom - big - 2 * (d - 1) * small, ..r },
+                                        _dt if dt & DT_BOTTOM == DT_BOTTOM => RECT { bottom: r.bottom - (after + rows) * small - big - (d - 1) * small, ..r },
+                                        _ => unimplemented!(),
+                                    }, s, dt, &style.context_color, style);
+                                }
+                            }
                             
-                            if let Some(s) = arc.lines.prev() {
-                                draw_text(hdc, match dt {
-                                    _dt if dt & DT_TOP == DT_TOP => r,
-                                    _dt if dt & DT_VCENTER == DT_VCENTER => RECT { bottom: r.bottom - big, ..r },
-                                    _dt if dt & DT_BOTTOM == DT_BOTTOM => RECT { bottom: r.bottom - small - big, ..r },
-                                    _ => unimplemented!(),
-                                }, s, dt);
+                            // d lines after the current line
+                            for d in 1..=after {
+                                if let Some(s) = arc.lines.relative(d as isize) {
+                                    draw_styled(hdc, match dt {
+                                        _dt if dt & DT_TOP == DT_TOP => RECT { top: r.top + before * small + big + (rows + d - 1) * small, ..r },
+                                        _dt if dt & DT_VCENTER == DT_VCENTER => RECT { top: r.top + big + 2 * (rows + d - 1) * small, ..r },
+                                        _dt if dt & DT_BOTTOM == DT_BOTTOM => RECT { bottom: r.bottom - (after - d) * small, ..r },
+                                        _ => unimplemented!(),
+                                    }, s, dt, &style.context_color, style);
+                                }
                             }
                             
-                            if let Some(s) = arc.lines.next() {
-                                draw_text(hdc, match dt {
-                                    _dt if dt & DT_TOP == DT_TOP => RECT { top: r.top + small + big, ..r },
-                                    _dt if dt & DT_VCENTER == DT_VCENTER => RECT { top: r.top + big, ..r },
-                                    _dt if dt & DT_BOTTOM == DT_BOTTOM => r,
-                                    _ => unimplemented!(),
-                                }, s, dt);
+                            for i in 0..rows {
+                                if let Some(s) = arc.lines.translation(i as usize) {
+                                    draw_styled(hdc, match dt {
+                                        _dt if dt & DT_TOP == DT_TOP => RECT { top: r.top + before * small + big + i * small, ..r },
+                                        _dt if dt & DT_VCENTER == DT_VCENTER => RECT { top: r.top + big + 2 * i * small, ..r },
+                                        _dt if dt & DT_BOTTOM == DT_BOTTOM => RECT { bottom: r.bottom - (after + rows - 1 - i) * small, ..r },
+                                        _ => unimplemented!(),
+                                    }, s, dt, &style.translation_color, style);
+                                }
                             }
                         });
                     }
@@ -604,6 +1869,18 @@ mod windows {
         }
     }
     
+    // The window covers the whole monitor, the first one if there are fewer monitors
+    #[inline(always)]
+    fn place(w: &Window<LyricsArc>, monitor: usize) -> Result<()> {
+        let monitors = monitors();
+        
+        if let Some(&r) = monitors.get(monitor).or(monitors.first()) {
+            w.move_to(r)?;
+        }
+        
+        Ok(())
+    }
+    
     #[repr(transparent)]
     pub struct WindowsLyrics(Window<LyricsArc>);
     
@@ -611,18 +1888,32 @@ mod windows {
         type Error = Error;
         
         fn new(layout: LyricsLayout) -> Result<Self> where Self: Sized {
-            Ok(Self(Window::new::<PaintCallback<_>>(
+            let monitor = layout.monitor;
+            let w = Window::new::<PaintCallback<_>>(
                 LyricsArc {
                     lines: Lines::new(),
                     layout,
                 })
-            ?))
+            ?;
+            
+            place(&w, monitor)?;
+            
+            Ok(Self(w))
         }
         
-        fn set_layout(&mut self, layout: LyricsLayout) -> Result<()> {
+        fn set_layout(&mut self, layout: &LyricsLayout) -> Result<()> {
             let Self(w) = self;
+            let mut moved = false;
             
-            if w.try_write(|arc| if arc.layout != layout { arc.layout = layout; w.redraw(); }) {
+            if w.try_write(|arc| if &arc.layout != layout {
+                moved = arc.layout.monitor != layout.monitor;
+                arc.layout = layout.clone();
+                w.redraw();
+            }) {
+                if moved {
+                    place(w, layout.monitor)?;
+                }
+                
                 Ok(())
             }
             else {
@@ -642,6 +1933,18 @@ mod windows {
             }
         }
         
+        fn set_translations(&mut self, translations: Vec<Vec<(Duration, String)>>) -> Result<()> {
+            let Self(w) = self;
+            
+            if w.try_write(|arc| arc.lines.set_translations(translations)) {
+                w.redraw();
+                Ok(())
+            }
+            else {
+                Err(Error::LockError("Write Lock Error"))
+            }
+        }
+        
         fn update(&mut self, time: Duration) -> Result<()> {
             let Self(w) = self;
             
@@ -672,5 +1975,107 @@ mod windows {
             
             Ok(())
         }
+        
+        #[inline(always)]
+        fn monitor_count(&self) -> usize {
+            monitors().len().max(1)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn group(entries: &[(u64, &str)]) -> Vec<(u64, String, usize)> {
+        Line::group(entries.iter().map(|&(t, s)| (Duration::from_millis(t), s.to_string())).collect())
+            .into_iter()
+            .map(|line| (line.start().as_millis() as u64, line.text, line.words.len()))
+            .collect()
+    }
+    
+    fn lines(expected: &[(u64, &str, usize)]) -> Vec<(u64, String, usize)> {
+        expected.iter().map(|&(t, s, n)| (t, s.to_string(), n)).collect()
+    }
+    
+    #[test]
+    fn leading_newlines() {
+        assert_eq!(group(&[(0, "\nHello"), (1000, "\nWorld")]), lines(&[(0, "Hello", 1), (1000, "World", 1)]));
+    }
+    
+    #[test]
+    fn trailing_newlines() {
+        assert_eq!(group(&[(0, "Hello\n"), (1000, "World\n")]), lines(&[(0, "Hello", 1), (1000, "World", 1)]));
+        assert_eq!(group(&[(0, "Hello\r\n"), (1000, "World\r\n")]), lines(&[(0, "Hello", 1), (1000, "World", 1)]));
+    }
+    
+    #[test]
+    fn syllables() {
+        assert_eq!(
+            group(&[(0, "\nHel"), (200, "lo "), (400, "world"), (1000, "\nAgain")]),
+            lines(&[(0, "Hello world", 3), (1000, "Again", 1)]),
+        );
+        assert_eq!(group(&[(0, "Hel"), (200, "lo"), (400, " "), (600, "world\r\n")]), lines(&[(0, "Hello world", 3)]));
+    }
+    
+    #[test]
+    fn spacers() {
+        assert_eq!(
+            group(&[(0, "\nHello"), (1000, "\n"), (5000, "\nWorld")]),
+            lines(&[(0, "Hello", 1), (1000, "", 1), (5000, "World", 1)]),
+        );
+        assert_eq!(
+            group(&[(0, "Hello\n"), (1000, ""), (5000, "World\n")]),
+            lines(&[(0, "Hello", 1), (1000, "", 1), (5000, "World", 1)]),
+        );
+        // consecutive spacers and spacers before the first line add nothing
+        assert_eq!(
+            group(&[(0, "\n"), (500, "\nHello"), (1000, "\n"), (2000, "\n"), (5000, "\nWorld")]),
+            lines(&[(500, "Hello", 1), (1000, "", 1), (5000, "World", 1)]),
+        );
+    }
+    
+    #[test]
+    fn blank_lrc_lines() {
+        let lrc = crate::lrc::Lrc::parse("[00:01.00]Hello\n[00:03.00]\n[00:05.00]World\n");
+        
+        assert_eq!(
+            Line::group(lrc.synced_lyrics()).into_iter().map(|line| line.text).collect::<Vec<_>>(),
+            ["Hello", "", "World"],
+        );
+    }
+    
+    #[test]
+    fn no_newlines() {
+        assert_eq!(group(&[(0, "Hello"), (1000, "World")]), lines(&[(0, "Hello", 1), (1000, "World", 1)]));
+    }
+    
+    #[test]
+    fn estimated_timing() {
+        let estimated = |length: u64, line: u64| Line::group(estimate_timing("\nOne\r\nTwo\n\nThree\n", Duration::from_secs(length), Duration::from_secs(line)))
+            .into_iter()
+            .map(|line| (line.start().as_secs(), line.text))
+            .collect::<Vec<_>>();
+        
+        assert_eq!(estimated(40, 0), [(0, "~ One".to_string()), (10, "~ Two".to_string()), (30, "~ Three".to_string())]);
+        assert_eq!(estimated(0, 3), [(0, "~ One".to_string()), (3, "~ Two".to_string()), (9, "~ Three".to_string())]);
+    }
+    
+    #[test]
+    fn multibyte_first_character() {
+        assert_eq!(group(&[(0, "日本"), (1000, "Étoile")]), lines(&[(0, "日本", 1), (1000, "Étoile", 1)]));
+        assert_eq!(group(&[(0, "\n日本"), (500, "語"), (1000, "\nÉtoile")]), lines(&[(0, "日本語", 2), (1000, "Étoile", 1)]));
+    }
+    
+    #[cfg(any(feature = "x11-lyrics", feature = "wayland-lyrics", feature = "windows-lyrics"))]
+    #[test]
+    fn multibyte_sung_part() {
+        let mut l = Lines::new();
+        
+        l.set(vec![(Duration::ZERO, "\n日本".to_string()), (Duration::from_millis(500), "語".to_string())]);
+        l.update(Duration::from_millis(600));
+        
+        assert_eq!(l.curr(), Some("日本語"));
+        assert_eq!(l.sung(), Some("日本語"));
     }
 }
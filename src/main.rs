@@ -1,6 +1,6 @@
 #![cfg_attr(not(feature = "console"), windows_subsystem = "windows")]
 
-use std::{env::current_exe, ffi::OsString, fs::{create_dir_all, read_to_string, write}, path::PathBuf, time::{Duration, Instant}};
+use std::{collections::HashMap, env::{args, current_exe}, ffi::OsString, fs::{create_dir_all, read_to_string, write}, path::PathBuf, time::{Duration, Instant}};
 
 mod gui;
 mod player;
@@ -9,6 +9,13 @@ mod history;
 mod playlist;
 mod lyrics;
 mod metadata;
+mod lrc;
+mod recorder;
+mod export;
+mod listening;
+mod undo;
+mod keybindings;
+mod settings;
 
 use gui::*;
 use player::*;
@@ -16,6 +23,12 @@ use playlist::*;
 use events::*;
 use lyrics::*;
 use metadata::*;
+use listening::*;
+use undo::UndoStack;
+use keybindings::Keybindings;
+use settings::Settings;
+use recorder::{LyricsRecorder, RecordFormat};
+use export::LyricsFormat;
 
 use serde_json::{from_str, to_string_pretty};
 use notify_rust::Notification;
@@ -24,7 +37,6 @@ use fslock::LockFile;
 
 pub trait BooleanConditional {
     fn ifdo(self, f: impl FnOnce()) -> Self;
-    fn elsedo(self, f: impl FnOnce()) -> Self;
 }
 
 impl BooleanConditional for bool {
@@ -35,14 +47,6 @@ impl BooleanConditional for bool {
         
         self
     }
-    
-    fn elsedo(self, f: impl FnOnce()) -> Self {
-        if !self {
-            f();
-        }
-        
-        self
-    }
 }
 
 
@@ -97,19 +101,40 @@ struct App {
     gui: Option<GUI>,
     player: Player,
     playlist: Playlist,
+    playlist_edits: UndoStack<PlaylistSnapshot>,
     listener: EventListener,
     lyrics: Option<Lyrics>,
+    lyrics_lines: Lines, // the synced lyrics shown, whether or not there are floating lyrics
+    listening: ListeningLog,
     
     fps: u16,
     delta: Duration,
     volume_step: f32,
     key_duration: Duration,
     seek_duration: Duration,
+    lyrics_offset_step: Duration,
+    lyrics_offset: i64, // in milliseconds, positive shows the lyrics earlier
+    lyrics_offsets: HashMap<String, i64>,
+    lyrics_source: Option<LyricsSource>,
+    recorder: Option<LyricsRecorder>,
+    record_lyrics_to: RecordFormat,
+    estimate_unsynced_lyrics: bool,
+    unsynced_line_duration: Duration,
+    unsynced_lyrics: Option<String>, // waiting for the length of the song to be known
+    lyrics_estimated: bool,
+    lyrics_backend: LyricsBackend,
     lyrics_layout: LyricsLayout,
+    settings_lyrics_layout: LyricsLayout, // the one in the settings, to tell when it is changed
+    lyrics_languages: Vec<String>,
+    lyrics_language: Option<String>, // chosen for this session, tried before the preferred languages
+    translation_languages: Vec<String>,
+    lyrics_dir: Option<PathBuf>,
+    prefer_lrc: bool,
     cache_path: PathBuf,
     
     toggle_gui: Option<usize>,
     quit_app: Option<usize>,
+    reload_settings: Option<usize>,
     pause_resume_song: Option<usize>,
     stop_player: Option<usize>,
     volume_increase: Option<usize>,
@@ -123,7 +148,19 @@ struct App {
     jump_to_begin: Option<usize>,
     jump_to_end: Option<usize>,
     prev_song: Option<usize>,
+    next_song: Option<usize>,
     toggle_lyrics_visibility: Option<usize>,
+    next_lyrics_language: Option<usize>,
+    lyrics_earlier: Option<usize>,
+    lyrics_later: Option<usize>,
+    save_lyrics_offset: Option<usize>,
+    toggle_lyrics_recording: Option<usize>,
+    stamp_lyrics_line: Option<usize>,
+    undo_lyrics_stamp: Option<usize>,
+    prev_lyrics_line: Option<usize>,
+    next_lyrics_line: Option<usize>,
+    undo_playlist_edit: Option<usize>,
+    redo_playlist_edit: Option<usize>,
     
     lyrics_top_left: Option<usize>,
     lyrics_top_center: Option<usize>,
@@ -134,6 +171,7 @@ struct App {
     lyrics_bottom_left: Option<usize>,
     lyrics_bottom_center: Option<usize>,
     lyrics_bottom_right: Option<usize>,
+    cycle_lyrics_monitor: Option<usize>,
     
     request_duration: bool,
     stop_next: bool,
@@ -141,7 +179,8 @@ struct App {
 
 
 impl App {
-    fn run() {
+    #[inline(always)]
+    fn cache_path() -> PathBuf {
         let mut cache_path = dirs::cache_dir().unwrap();
                 
         cache_path.push("feois-music-player");
@@ -150,29 +189,71 @@ impl App {
             create_dir_all(&cache_path).expect("Failed to create cache directory");
         }
         
+        cache_path
+    }
+    
+    #[inline(always)]
+    fn config_path() -> PathBuf {
+        let mut config_path = dirs::config_dir().unwrap();
+        
+        config_path.push("feois-music-player");
+        
+        if !config_path.is_dir() {
+            create_dir_all(&config_path).expect("Failed to create config directory");
+        }
+        
+        config_path
+    }
+    
+    fn run() {
+        let cache_path = App::cache_path();
+        
         let mut lock = LockFile::open(cache_path.as_path().join("instance.lock").as_os_str()).expect("Failed to create lock");
         
         if !lock.try_lock_with_pid().expect("Failed to lock file") {
             return;
         }
         
+        let settings = App::load_settings().unwrap_or_default();
+        
         let mut app = App {
             gui: None,
-            playlist: Playlist::new(100),
+            playlist: Playlist::new(settings.history_capacity),
+            playlist_edits: UndoStack::new(settings.undo_capacity),
             player: Player::new(),
             listener: EventListener::listen(),
             lyrics: None,
+            lyrics_lines: Lines::new(),
+            listening: ListeningLog::new(cache_path.as_path().join("listening.log")),
             
-            fps: 120,
+            fps: settings.fps,
             delta: Duration::ZERO,
-            volume_step: 0.05,
-            key_duration: Duration::from_millis(100),
-            seek_duration: Duration::from_secs(5),
-            lyrics_layout: LyricsLayout { position: LyricsPosition::TopCenter, margin: 48, visible: true },
+            volume_step: settings.volume_step,
+            key_duration: settings.key_duration,
+            seek_duration: settings.seek_duration,
+            lyrics_offset_step: settings.lyrics_offset_step,
+            lyrics_offset: 0,
+            lyrics_offsets: HashMap::new(),
+            lyrics_source: None,
+            recorder: None,
+            record_lyrics_to: settings.record_lyrics_to,
+            estimate_unsynced_lyrics: settings.estimate_unsynced_lyrics,
+            unsynced_line_duration: settings.unsynced_line_duration,
+            unsynced_lyrics: None,
+            lyrics_estimated: false,
+            lyrics_backend: settings.lyrics_backend,
+            lyrics_layout: settings.lyrics_layout.clone(),
+            settings_lyrics_layout: settings.lyrics_layout.clone(),
+            lyrics_languages: settings.lyrics_languages.clone(),
+            lyrics_language: None,
+            translation_languages: settings.translation_languages.clone(),
+            lyrics_dir: settings.lyrics_dir.clone(),
+            prefer_lrc: settings.prefer_lrc,
             cache_path,
             
             toggle_gui: None,
             quit_app: None,
+            reload_settings: None,
             pause_resume_song: None,
             stop_player: None,
             volume_increase: None,
@@ -186,7 +267,19 @@ impl App {
             jump_to_begin: None,
             jump_to_end: None,
             prev_song: None,
+            next_song: None,
             toggle_lyrics_visibility: None,
+            next_lyrics_language: None,
+            lyrics_earlier: None,
+            lyrics_later: None,
+            save_lyrics_offset: None,
+            toggle_lyrics_recording: None,
+            stamp_lyrics_line: None,
+            undo_lyrics_stamp: None,
+            prev_lyrics_line: None,
+            next_lyrics_line: None,
+            undo_playlist_edit: None,
+            redo_playlist_edit: None,
             
             lyrics_top_left: None,
             lyrics_top_center: None,
@@ -197,6 +290,7 @@ impl App {
             lyrics_bottom_left: None,
             lyrics_bottom_center: None,
             lyrics_bottom_right: None,
+            cycle_lyrics_monitor: None,
             
             request_duration: false,
             stop_next: false,
@@ -205,70 +299,118 @@ impl App {
         let playlist_cache_path = app.cache_path.as_path().join("playlist.json");
         let player_cache_path = app.cache_path.as_path().join("player.json");
         let lyrics_cache_path = app.cache_path.as_path().join("lyrics.json");
+        let lyrics_offsets_cache_path = app.cache_path.as_path().join("lyrics_offsets.json");
         
         if let Some(playlist) = read_to_string(&playlist_cache_path).ok().and_then(|s| from_str(&s).ok()) {
             app.playlist = playlist;
-            unsafe { app.playlist.history_keep_at_most(100) };
+            app.playlist.history_keep_at_most(settings.history_capacity);
         }
         
         if let Some(player) = read_to_string(&player_cache_path).ok().and_then(|s| from_str(&s).ok()) {
             app.player = player;
         }
         
-        if let Some(lp) = read_to_string(&lyrics_cache_path).ok().and_then(|s| from_str(&s).ok()) {
-            app.lyrics_layout = lp;
+        if let Some(lp) = read_to_string(&lyrics_cache_path).ok().and_then(|s| from_str::<LyricsLayout>(&s).ok()) {
+            match lp.style.validate() {
+                Ok(()) => app.lyrics_layout = lp,
+                Err(e) => error!(e, "Invalid lyrics style in {}", lyrics_cache_path.display()),
+            }
         }
         
-        match Lyrics::new(app.lyrics_layout) {
-            Ok(lyrics) => { app.lyrics.replace(lyrics); }
-            Err(e) => error!(e, "Failed to initialize floating lyrics")
+        if let Some(offsets) = read_to_string(&lyrics_offsets_cache_path).ok().and_then(|s| from_str(&s).ok()) {
+            app.lyrics_offsets = offsets;
         }
         
+        app.start_lyrics();
+        
         app.delta = Duration::from_secs_f64(1. / app.fps as f64);
         
-        let mut regonce = |keys: &[Key]| Some(app.listener.register_once_combination(keys));
-        
-        app.toggle_gui                  = regonce(&[Key::Alt, Key::KeyC]);
-        app.quit_app                    = regonce(&[Key::Alt, Key::KeyE]);
-        app.pause_resume_song           = regonce(&[Key::Alt, Key::Space]);
-        app.stop_player                 = regonce(&[Key::Alt, Key::ShiftLeft, Key::Space]);
-        app.toggle_repeat_mode          = regonce(&[Key::Alt, Key::KeyR]);
-        app.toggle_shuffling            = regonce(&[Key::Alt, Key::ShiftLeft, Key::KeyR]);
-        app.toggle_mute                 = regonce(&[Key::Alt, Key::KeyM]);
-        app.toggle_stop_next            = regonce(&[Key::Alt, Key::ShiftLeft, Key::KeyM]);
-        app.jump_to_begin               = regonce(&[Key::Alt, Key::ControlLeft, Key::LeftArrow]);
-        app.jump_to_end                 = regonce(&[Key::Alt, Key::ControlLeft, Key::RightArrow]);
-        app.prev_song                   = regonce(&[Key::Alt, Key::ControlLeft, Key::UpArrow]);
-        app.toggle_lyrics_visibility    = regonce(&[Key::Alt, Key::KeyH]);
-        
-        app.lyrics_top_left         = regonce(&[Key::Alt, Key::KeyL, Key::Num1]);
-        app.lyrics_top_center       = regonce(&[Key::Alt, Key::KeyL, Key::Num2]);
-        app.lyrics_top_right        = regonce(&[Key::Alt, Key::KeyL, Key::Num3]);
-        app.lyrics_center_left      = regonce(&[Key::Alt, Key::KeyL, Key::Num4]);
-        app.lyrics_center           = regonce(&[Key::Alt, Key::KeyL, Key::Num5]);
-        app.lyrics_center_right     = regonce(&[Key::Alt, Key::KeyL, Key::Num6]);
-        app.lyrics_bottom_left      = regonce(&[Key::Alt, Key::KeyL, Key::Num7]);
-        app.lyrics_bottom_center    = regonce(&[Key::Alt, Key::KeyL, Key::Num8]);
-        app.lyrics_bottom_right     = regonce(&[Key::Alt, Key::KeyL, Key::Num9]);
-        
-        let mut reg = |keys: &[Key], d| Some(app.listener.register_combination(keys, d));
-        
-        app.volume_increase = reg(&[Key::Alt, Key::UpArrow], app.key_duration);
-        app.volume_decrease = reg(&[Key::Alt, Key::DownArrow], app.key_duration);
-        app.rewind          = reg(&[Key::Alt, Key::LeftArrow], app.key_duration * 2);
-        app.fast_forward    = reg(&[Key::Alt, Key::RightArrow], app.key_duration * 2);
+        let (bindings, errors) = Keybindings::load(&App::config_path().join("keybindings.json"));
+        
+        for e in &errors {
+            error!(e, "Invalid keybindings:");
+        }
+        
+        if !errors.is_empty() {
+            show_notification(format!("Invalid keybindings: {}", errors[0]));
+        }
+        
+        app.listener.set_sequence_timeout(settings.sequence_timeout);
+        
+        let mut regonce = |action| bindings.get(action).map(|binding| app.listener.register(binding, Duration::MAX));
+        
+        app.toggle_gui                  = regonce("toggle_gui");
+        app.quit_app                    = regonce("quit_app");
+        app.reload_settings             = regonce("reload_settings");
+        app.pause_resume_song           = regonce("pause_resume_song");
+        app.stop_player                 = regonce("stop_player");
+        app.toggle_repeat_mode          = regonce("toggle_repeat_mode");
+        app.toggle_shuffling            = regonce("toggle_shuffling");
+        app.toggle_mute                 = regonce("toggle_mute");
+        app.toggle_stop_next            = regonce("toggle_stop_next");
+        app.jump_to_begin               = regonce("jump_to_begin");
+        app.jump_to_end                 = regonce("jump_to_end");
+        app.prev_song                   = regonce("prev_song");
+        app.next_song                   = regonce("next_song");
+        app.toggle_lyrics_visibility    = regonce("toggle_lyrics_visibility");
+        app.next_lyrics_language        = regonce("next_lyrics_language");
+        app.lyrics_earlier              = regonce("lyrics_earlier");
+        app.lyrics_later                = regonce("lyrics_later");
+        app.save_lyrics_offset          = regonce("save_lyrics_offset");
+        app.toggle_lyrics_recording     = regonce("toggle_lyrics_recording");
+        app.stamp_lyrics_line           = regonce("stamp_lyrics_line");
+        app.undo_lyrics_stamp           = regonce("undo_lyrics_stamp");
+        app.prev_lyrics_line            = regonce("prev_lyrics_line");
+        app.next_lyrics_line            = regonce("next_lyrics_line");
+        app.undo_playlist_edit          = regonce("undo_playlist_edit");
+        app.redo_playlist_edit          = regonce("redo_playlist_edit");
+        
+        app.lyrics_top_left         = regonce("lyrics_top_left");
+        app.lyrics_top_center       = regonce("lyrics_top_center");
+        app.lyrics_top_right        = regonce("lyrics_top_right");
+        app.lyrics_center_left      = regonce("lyrics_center_left");
+        app.lyrics_center           = regonce("lyrics_center");
+        app.lyrics_center_right     = regonce("lyrics_center_right");
+        app.lyrics_bottom_left      = regonce("lyrics_bottom_left");
+        app.lyrics_bottom_center    = regonce("lyrics_bottom_center");
+        app.lyrics_bottom_right     = regonce("lyrics_bottom_right");
+        app.cycle_lyrics_monitor    = regonce("cycle_lyrics_monitor");
+        
+        let mut reg = |action, d| bindings.get(action).map(|binding| app.listener.register(binding, d));
+        
+        app.volume_increase = reg("volume_increase", app.key_duration);
+        app.volume_decrease = reg("volume_decrease", app.key_duration);
+        app.rewind          = reg("rewind", app.key_duration * 2);
+        app.fast_forward    = reg("fast_forward", app.key_duration * 2);
+        
+        app.apply_repeat_acceleration(settings.repeat_acceleration);
+        app.listener.set_gesture_durations(settings.long_press_duration, settings.double_tap_interval);
         
         app.launch_gui();
         
+        let mut last = Instant::now();
+        
         loop {
             let t = Instant::now();
+            let frame = t - last;
+            
+            last = t;
             
             app.player.update_state();
             
+            // the length of the song is only needed to spread the lines over it
+            if app.unsynced_lyrics.is_some() && (!app.unsynced_line_duration.is_zero() || app.player.get_length() != Duration::ZERO) {
+                app.show_estimated_lyrics();
+            }
+            
             match app.player.get_state() {
-                PlayerState::Play => app.update_lyrics(app.player.get_position()),
+                PlayerState::Play => {
+                    app.listening.tick(frame);
+                    app.update_lyrics(app.player.get_position());
+                }
                 PlayerState::Finished => {
                     app.player.idle();
+                    app.listening.finish(true);
                     
                     let song = app.playlist.poll();
                     
@@ -294,7 +436,7 @@ impl App {
                     error!(e, "Failed to refresh lyrics");
                 }
                 
-                if let Err(e) = lyrics.set_layout(app.lyrics_layout) {
+                if let Err(e) = lyrics.set_layout(&app.lyrics_layout) {
                     error!(e, "Failed to reposition lyrics");
                 }
             }
@@ -307,15 +449,97 @@ impl App {
             }
         }
         
+        app.listening.finish(false);
+        
         write(playlist_cache_path, to_string_pretty(&app.playlist).expect("Failed to serialize")).expect("Failed to save cache");
         write(player_cache_path, to_string_pretty(&app.player).expect("Failed to serialize")).expect("Failed to save cache");
         write(lyrics_cache_path, to_string_pretty(&app.lyrics_layout).expect("Failed to serialize")).expect("Failed to save cache");
+        write(lyrics_offsets_cache_path, to_string_pretty(&app.lyrics_offsets).expect("Failed to serialize")).expect("Failed to save cache");
         
         status!("Exiting");
         
         lock.unlock().expect("Failed to unlock");
     }
     
+    #[inline(always)]
+    fn load_settings() -> Option<Settings> {
+        Settings::load(&App::config_path().join("settings.json"))
+            .inspect_err(|e| {
+                error!(e, "Failed to load settings:");
+                show_notification(e.to_string());
+            })
+            .ok()
+    }
+    
+    // Only seeking speeds up while held, the volume changes at a steady pace
+    #[inline(always)]
+    fn apply_repeat_acceleration(&mut self, acceleration: f64) {
+        for comb in [self.rewind, self.fast_forward].into_iter().flatten() {
+            self.listener.set_acceleration(comb, acceleration);
+        }
+    }
+    
+    // Returns whether the settings were loaded
+    fn reload_settings(&mut self) -> bool {
+        if let Some(settings) = App::load_settings() {
+            self.fps = settings.fps;
+            self.delta = Duration::from_secs_f64(1. / self.fps as f64);
+            self.volume_step = settings.volume_step;
+            self.key_duration = settings.key_duration;
+            self.seek_duration = settings.seek_duration;
+            self.lyrics_offset_step = settings.lyrics_offset_step;
+            self.lyrics_languages = settings.lyrics_languages;
+            self.translation_languages = settings.translation_languages;
+            self.lyrics_dir = settings.lyrics_dir;
+            self.prefer_lrc = settings.prefer_lrc;
+            self.record_lyrics_to = settings.record_lyrics_to;
+            self.estimate_unsynced_lyrics = settings.estimate_unsynced_lyrics;
+            self.unsynced_line_duration = settings.unsynced_line_duration;
+            
+            self.playlist.history_keep_at_most(settings.history_capacity);
+            self.playlist_edits.set_capacity(settings.undo_capacity);
+            self.listener.set_sequence_timeout(settings.sequence_timeout);
+            self.listener.set_gesture_durations(settings.long_press_duration, settings.double_tap_interval);
+            self.apply_repeat_acceleration(settings.repeat_acceleration);
+            
+            for (comb, duration) in [
+                (self.volume_increase, self.key_duration),
+                (self.volume_decrease, self.key_duration),
+                (self.rewind, self.key_duration * 2),
+                (self.fast_forward, self.key_duration * 2),
+            ] {
+                if let Some(comb) = comb {
+                    self.listener.set_duration(comb, duration);
+                }
+            }
+            
+            if self.lyrics_backend != settings.lyrics_backend {
+                self.lyrics_backend = settings.lyrics_backend;
+                self.start_lyrics();
+                
+                if let Some(song) = self.playlist.get_history().get_current().cloned() {
+                    if let PlayerState::Play | PlayerState::Pause = self.player.get_state() {
+                        self.show_lyrics(&song);
+                        self.update_lyrics(self.player.get_position());
+                    }
+                }
+            }
+            
+            // the layout is otherwise kept as it was last changed
+            if self.settings_lyrics_layout != settings.lyrics_layout {
+                self.lyrics_layout = settings.lyrics_layout.clone();
+                self.settings_lyrics_layout = settings.lyrics_layout;
+            }
+            
+            status!("Settings reloaded");
+            
+            true
+        }
+        else {
+            false
+        }
+    }
+    
     fn gui_events(&mut self) -> bool {
         let mut close = false;
         
@@ -324,23 +548,46 @@ impl App {
             self.request_duration = false;
         }
         
+        // every edit received at once is undone together, e.g. adding an album, the frontend groups
+        // the edits that can be received over several frames, e.g. switching the playing playlist
+        self.playlist_edits.begin_group();
+        
         while let Some(command) = self.gui.as_ref().and_then(GUI::read) {
             let (command_name, args) = command.split_once(' ').unwrap_or((&command, ""));
             
+            if matches!(command_name, "PLAYING" | "APPEND" | "UPDATE" | "MOVE" | "DELETE" | "DELETE_ALL") {
+                self.playlist_edits.record(|| self.playlist.snapshot());
+            }
+            
             match command_name {
                 "MARGIN" => self.lyrics_layout.margin = args.parse().unwrap(),
+                "LYRICS_MONITOR" => match args.parse() {
+                    Ok(monitor) => self.lyrics_layout.monitor = monitor,
+                    Err(e) => error!(e, "Cannot parse lyrics monitor {}", args),
+                }
+                "LYRICS_LINES" => match args.split_once(' ').map(|(before, after)| (before.parse(), after.parse())) {
+                    Some((Ok(before), Ok(after))) => { self.lyrics_layout.lines_before = before; self.lyrics_layout.lines_after = after }
+                    _ => error!("Cannot parse lyrics lines {}", args),
+                }
                 "READTAG" => self.read_tags(args),
                 "PLAY" => {
                     let song = &self.playlist.select(args.parse().unwrap()).to_string();
+                    let played = self.player.play(song);
                     
-                    self.player.play(song);
                     self.request_duration = true;
+                    self.start_listening(song, played);
                     self.show_lyrics(song);
                 }
-                "STOP" => { self.player.stop(); self.clear_lyrics() }
+                "STOP" => { self.listening.finish(false); self.player.stop(); self.clear_lyrics() }
                 "REPLAY" => if let Some(song) = self.playlist.get_history().get_current().cloned() { self.play(song) }
                 "PREV" => if let Some(song) = self.playlist.look_back() { self.play(song); }
-                "SKIP" => self.player.skip(),
+                "NEXT" => if let Some(song) = self.playlist.look_forward() { self.play(song); }
+                "HISTORY" => self.gui(GUICommand::HISTORY(self.playlist.get_history().iter().cloned().collect(), self.playlist.get_history().cursor())),
+                "HISTORY_JUMP" => match args.parse() {
+                    Ok(i) => if let Some(song) = self.playlist.history_jump(i) { self.play(song); }
+                    Err(e) => error!(e, "Cannot parse history index"),
+                }
+                "SKIP" => { self.listening.finish(false); self.player.skip() }
                 "PAUSE" => self.player.pause(),
                 "RESUME" => self.player.resume(),
                 "MUTE" => { self.player.mute = true; self.player.update_volume() }
@@ -348,14 +595,37 @@ impl App {
                 "VOLUME" => if let Err(e) = args.parse().map(|v| self.volume(v, false)) { error!(e, "Cannot parse volume") }
                 "VOLINC" => self.volume(self.player.volume + self.volume_step, true),
                 "VOLDEC" => self.volume(self.player.volume - self.volume_step, true),
+                "PLAYING" => self.playlist.id = args.to_string(),
                 "APPEND" => self.playlist.append(args.to_string()),
                 "UPDATE" => { let (i, path) = args.split_once(' ').unwrap(); self.playlist.update(i.parse().unwrap(), path.to_string()) }
                 "MOVE" => { let (from, to) = args.split_once(' ').unwrap(); self.playlist.arrange(from.parse().unwrap(), to.parse().unwrap()) }
                 "DELETE" => self.playlist.delete(args.parse().unwrap()),
                 "DELETE_ALL" => self.playlist.clear(),
+                "BEGIN_EDIT" => self.playlist_edits.begin_group(),
+                "END_EDIT" => self.playlist_edits.end_group(),
+                "UNDO" => self.undo_playlist_edit(),
+                "REDO" => self.redo_playlist_edit(),
                 "TOGGLE_REPEAT" => { self.playlist.toggle_repeat_mode(); self.gui(GUICommand::REPEAT(self.playlist.get_repeat_mode())) },
                 "SHUFFLE" => self.playlist.shuffle = true,
                 "NO_SHUFFLE" => self.playlist.shuffle = false,
+                "SEED" => if let Err(e) = args.parse().map(|seed| self.playlist.seed(seed)) { error!(e, "Cannot parse seed") }
+                "UPCOMING" => if let Err(e) = args.parse().map(|n| self.gui(GUICommand::UPCOMING(self.playlist.upcoming(n)))) { error!(e, "Cannot parse count") }
+                "RELOAD_SETTINGS" => { self.reload_settings(); }
+                "LYRICS_EARLIER" => self.set_lyrics_offset(self.lyrics_offset + self.lyrics_offset_step.as_millis() as i64, false),
+                "LYRICS_LATER" => self.set_lyrics_offset(self.lyrics_offset - self.lyrics_offset_step.as_millis() as i64, false),
+                "LYRICS_OFFSET" => if let Err(e) = args.parse().map(|offset| self.set_lyrics_offset(offset, false)) { error!(e, "Cannot parse lyrics offset") }
+                "SAVE_LYRICS_OFFSET" => self.save_lyrics_offset(),
+                "EXPORT_LYRICS" => self.export_lyrics(args),
+                "RECORD_LYRICS" => self.toggle_lyrics_recording(),
+                "STAMP_LYRICS" => self.stamp_lyrics_line(),
+                "UNDO_STAMP" => self.undo_lyrics_stamp(),
+                "DISCARD_RECORDING" => self.discard_lyrics_recording(),
+                "LYRICS" => self.push_lyrics(),
+                "SEEK_LINE" => match args.parse() {
+                    Ok(i) => self.seek_line(i),
+                    Err(e) => error!(e, "Cannot parse line index"),
+                }
+                "LYRICS_LANGUAGE" => self.set_lyrics_language(args.trim().to_string()),
                 "INFO" => println!("GODOT-PRINT: {}", args),
                 "REWIND" => self.rewind(),
                 "FAST_FORWARD" => self.fast_forward(),
@@ -366,6 +636,8 @@ impl App {
             }
         }
         
+        self.playlist_edits.end_group();
+        
         if close || self.gui.as_mut().is_some_and(GUI::finished) {
             self.close_gui();
         }
@@ -390,6 +662,10 @@ impl App {
                 return true;
             }
             
+            if comb == self.reload_settings && self.reload_settings() {
+                show_notification("Settings reloaded");
+            }
+            
             if comb == self.pause_resume_song {
                 match self.player.get_state() {
                     PlayerState::Idle => {
@@ -410,6 +686,7 @@ impl App {
             }
             
             if comb == self.stop_player {
+                self.listening.finish(false);
                 self.gui(GUICommand::STOP);
                 self.player.stop();
                 self.clear_lyrics();
@@ -463,6 +740,7 @@ impl App {
             }
             
             if comb == self.jump_to_end {
+                self.listening.finish(false);
                 self.gui(GUICommand::STOP);
                 self.player.skip();
             }
@@ -475,10 +753,61 @@ impl App {
                 }
             }
             
+            if comb == self.next_song {
+                if let Some(song) = self.playlist.look_forward() {
+                    self.gui(GUICommand::STOP);
+                    self.play(song);
+                }
+            }
+            
+            if comb == self.undo_playlist_edit {
+                self.undo_playlist_edit();
+            }
+            
+            if comb == self.redo_playlist_edit {
+                self.redo_playlist_edit();
+            }
+            
             if comb == self.toggle_lyrics_visibility {
                 self.lyrics_layout.visible = !self.lyrics_layout.visible;
             }
             
+            if comb == self.next_lyrics_language {
+                self.next_lyrics_language();
+            }
+            
+            if comb == self.lyrics_earlier {
+                self.set_lyrics_offset(self.lyrics_offset + self.lyrics_offset_step.as_millis() as i64, true);
+            }
+            
+            if comb == self.lyrics_later {
+                self.set_lyrics_offset(self.lyrics_offset - self.lyrics_offset_step.as_millis() as i64, true);
+            }
+            
+            if comb == self.save_lyrics_offset {
+                self.save_lyrics_offset();
+            }
+            
+            if comb == self.toggle_lyrics_recording {
+                self.toggle_lyrics_recording();
+            }
+            
+            if comb == self.stamp_lyrics_line {
+                self.stamp_lyrics_line();
+            }
+            
+            if comb == self.undo_lyrics_stamp {
+                self.undo_lyrics_stamp();
+            }
+            
+            if comb == self.prev_lyrics_line {
+                self.seek_line(self.lyrics_lines.current().map_or(0, |i| i.saturating_sub(1)));
+            }
+            
+            if comb == self.next_lyrics_line {
+                self.seek_line(self.lyrics_lines.current().map_or(0, |i| i + 1));
+            }
+            
             if comb == self.lyrics_top_left {
                 self.lyrics_layout.position = LyricsPosition::TopLeft;
             }
@@ -514,6 +843,16 @@ impl App {
             if comb == self.lyrics_bottom_right {
                 self.lyrics_layout.position = LyricsPosition::BottomRight;
             }
+            
+            if comb == self.cycle_lyrics_monitor {
+                let count = self.lyrics.as_mut().map_or(1, |lyrics| {
+                    lyrics.refresh_monitors();
+                    lyrics.monitor_count()
+                });
+                
+                self.lyrics_layout.monitor = (self.lyrics_layout.monitor + 1) % count;
+                show_notification(format!("Lyrics monitor: {}", self.lyrics_layout.monitor));
+            }
         }
         
         false
@@ -537,6 +876,7 @@ impl App {
         let mut args = vec![
             arg!("cache-path" &self.cache_path),
             arg!("lyrics-margin" self.lyrics_layout.margin.to_string()),
+            arg!("lyrics-lines" format!("{} {}", self.lyrics_layout.lines_before, self.lyrics_layout.lines_after)),
             arg!("fps" self.fps.to_string()),
         ];
         
@@ -567,16 +907,19 @@ impl App {
         gui.command(GUICommand::REPEAT(self.playlist.get_repeat_mode()));
         
         self.gui.replace(gui);
+        self.push_lyrics();
     }
     
     #[inline(always)]
     fn close_gui(&mut self) {
         self.gui.take().map(GUI::close);
+        // a group left open by the frontend would swallow the edits of the next one
+        self.playlist_edits.end_all_groups();
     }
     
     #[inline(always)]
     fn read_tags(&mut self, path: &str) {
-        match Song::new(path) {
+        match Song::new(path, &self.preferred_languages()) {
             Ok(song) => self.gui(GUICommand::TAGOF(song)),
             Err(e) => error!(e, "Failed to read tags {}", path),
         }
@@ -592,6 +935,26 @@ impl App {
         }
     }
     
+    #[inline(always)]
+    fn undo_playlist_edit(&mut self) {
+        if let Some(snapshot) = self.playlist_edits.undo(|| self.playlist.snapshot()) {
+            self.playlist.restore(snapshot);
+            self.gui(GUICommand::PLAYLIST(self.playlist.id.clone(), self.playlist.get_songs().to_vec()));
+            
+            show_notification("Undo playlist edit");
+        }
+    }
+    
+    #[inline(always)]
+    fn redo_playlist_edit(&mut self) {
+        if let Some(snapshot) = self.playlist_edits.redo(|| self.playlist.snapshot()) {
+            self.playlist.restore(snapshot);
+            self.gui(GUICommand::PLAYLIST(self.playlist.id.clone(), self.playlist.get_songs().to_vec()));
+            
+            show_notification("Redo playlist edit");
+        }
+    }
+    
     #[inline(always)]
     fn rewind(&mut self) {
         self.player.rewind(self.seek_duration);
@@ -612,8 +975,61 @@ impl App {
         self.update_lyrics(time);
     }
     
+    // The position in the song where a line of the lyrics starts, the offset shifts it the opposite way
+    #[inline(always)]
+    fn line_position(&self, time: Duration) -> Duration {
+        if self.lyrics_offset >= 0 {
+            time.saturating_sub(Duration::from_millis(self.lyrics_offset as u64))
+        }
+        else {
+            time + Duration::from_millis(self.lyrics_offset.unsigned_abs())
+        }
+    }
+    
+    // Each line of the synced lyrics shown and where it starts in the song, lines not yet stamped while recording start nowhere
     #[inline(always)]
-    fn update_lyrics(&mut self, time: Duration) {       
+    fn timed_lyrics(&self) -> Vec<(Option<Duration>, String)> {
+        self.lyrics_lines.get().iter().map(|line| {
+            (Some(line.start()).filter(|&start| start != Duration::MAX).map(|start| self.line_position(start)), line.text.clone())
+        }).collect()
+    }
+    
+    // The frontend is told the current line again whenever it changes
+    #[inline(always)]
+    fn push_lyrics(&mut self) {
+        self.gui(GUICommand::LYRICS(self.timed_lyrics()));
+        self.gui(GUICommand::LYRICS_ESTIMATED(self.lyrics_estimated));
+        self.gui(GUICommand::LYRICS_LINE(self.lyrics_lines.current()));
+    }
+    
+    // Lines not yet stamped while recording have no position to seek to
+    #[inline(always)]
+    fn seek_line(&mut self, i: usize) {
+        if let PlayerState::Play | PlayerState::Pause = self.player.get_state() {
+            if let Some(start) = self.lyrics_lines.get().get(i).map(Line::start).filter(|&start| start != Duration::MAX) {
+                let position = self.line_position(start);
+                
+                self.seek(position);
+                self.gui(GUICommand::SEEK(position));
+            }
+        }
+    }
+    
+    #[inline(always)]
+    fn update_lyrics(&mut self, time: Duration) {
+        let time = if self.lyrics_offset >= 0 {
+            time + Duration::from_millis(self.lyrics_offset as u64)
+        }
+        else {
+            time.saturating_sub(Duration::from_millis(self.lyrics_offset.unsigned_abs()))
+        };
+        
+        let line = self.lyrics_lines.current();
+        
+        if self.lyrics_lines.update(time) && self.lyrics_lines.current() != line {
+            self.gui(GUICommand::LYRICS_LINE(self.lyrics_lines.current()));
+        }
+        
         if let Some(lyrics) = &mut self.lyrics {
             if let Err(e) = lyrics.update(time) {
                 error!(e, "Failed to update lyrics")
@@ -623,25 +1039,289 @@ impl App {
     
     #[inline(always)]
     fn play(&mut self, song: String) {
-        self.player.play(&song);
+        if self.recorder.as_ref().is_some_and(|recorder| recorder.get_song() != song) {
+            self.discard_lyrics_recording();
+        }
+        
+        let played = self.player.play(&song);
+        
         self.request_duration = true;
+        self.start_listening(&song, played);
         self.show_lyrics(&song);
         self.gui(GUICommand::PLAY(song));
     }
     
+    // A song that failed to play is not logged, the state is still the one of the song before
+    #[inline(always)]
+    fn start_listening(&mut self, song: &str, played: bool) {
+        if played {
+            self.listening.start(song);
+        }
+    }
+    
     #[inline(always)]
     fn show_lyrics(&mut self, path: &str) {
-        if let Some(lyrics) = &mut self.lyrics {
-            match Song::synced_lyrics(path) {
-                Ok(Some(l)) => if let Err(e) = lyrics.set_lyrics(l) { error!(e, "Failed to display lyrics") }
-                Ok(None) => {}
-                Err(e) => error!(e, "Failed to read tag from {}", path)
+        self.lyrics_offset = self.lyrics_offsets.get(path).copied().unwrap_or(0);
+        self.lyrics_source = None;
+        self.lyrics_lines = Lines::new();
+        self.lyrics_estimated = false;
+        self.unsynced_lyrics = None;
+        
+        let result = match Song::load_synced_lyrics(path, self.lyrics_dir.as_deref(), self.prefer_lrc, &self.preferred_languages()) {
+            Some((source, l)) => {
+                self.lyrics_source.replace(source);
+                self.lyrics_lines.set(l.clone());
+                
+                // the lyrics themselves can be in a translation language when no preferred language is found
+                let translations = self.translation_languages.iter()
+                    .filter_map(|lang| Song::load_translation(path, self.lyrics_dir.as_deref(), self.prefer_lrc, lang))
+                    .filter(|t| t != &l)
+                    .collect();
+                
+                self.lyrics.as_mut().map_or(Ok(()), |lyrics| lyrics.set_lyrics(l).and_then(|_| lyrics.set_translations(translations)))
+            }
+            None => {
+                if self.estimate_unsynced_lyrics {
+                    match Song::unsynced_lyrics(path, &self.preferred_languages()) {
+                        Ok(lyrics) => self.unsynced_lyrics = lyrics.map(|(_, text)| text),
+                        Err(e) => error!(e, "Failed to read tag from {}", path),
+                    }
+                }
+                
+                self.lyrics.as_mut().map_or(Ok(()), |lyrics| lyrics.clear())
+            }
+        };
+        
+        if let Err(e) = result {
+            error!(e, "Failed to display lyrics")
+        }
+        
+        self.push_lyrics();
+    }
+    
+    // Unsynced lyrics are timed once the length of the song is known if needed, the offset still applies to them
+    #[inline(always)]
+    fn show_estimated_lyrics(&mut self) {
+        if let Some(text) = self.unsynced_lyrics.take() {
+            let l = estimate_timing(&text, self.player.get_length(), self.unsynced_line_duration);
+            
+            self.lyrics_estimated = true;
+            self.lyrics_lines.set(l.clone());
+            
+            if let Some(lyrics) = &mut self.lyrics {
+                if let Err(e) = lyrics.set_lyrics(l) {
+                    error!(e, "Failed to display lyrics")
+                }
+            }
+            
+            self.push_lyrics();
+            self.update_lyrics(self.player.get_position());
+        }
+    }
+    
+    // The offset is remembered for the current song
+    #[inline(always)]
+    fn set_lyrics_offset(&mut self, offset: i64, notify: bool) {
+        if let Some(song) = self.playlist.get_history().get_current().cloned() {
+            self.lyrics_offset = offset;
+            
+            if offset == 0 {
+                self.lyrics_offsets.remove(&song);
+            }
+            else {
+                self.lyrics_offsets.insert(song, offset);
+            }
+            
+            self.update_lyrics(self.player.get_position());
+            self.push_lyrics();
+            
+            if notify {
+                show_notification(format!("Lyrics offset: {:+} ms", offset));
+            }
+        }
+    }
+    
+    // Writes the offset into the lyrics themselves, as the [offset:] of a .lrc file or by shifting the SYLT timestamps
+    #[inline(always)]
+    fn save_lyrics_offset(&mut self) {
+        if let (Some(song), Some(source)) = (self.playlist.get_history().get_current().cloned(), &self.lyrics_source) {
+            if self.lyrics_offset != 0 {
+                match source.write_offset(&song, self.lyrics_offset) {
+                    Ok(()) => {
+                        self.lyrics_offsets.remove(&song);
+                        self.show_lyrics(&song);
+                        self.update_lyrics(self.player.get_position());
+                        
+                        show_notification("Lyrics offset saved");
+                    }
+                    Err(e) => error!(e, "Failed to save lyrics offset of {}", song),
+                }
+            }
+        }
+    }
+    
+    // EXPORT_LYRICS <lrc|srt|txt> <song or directory>, the lyrics are written next to the songs without replacing any file
+    #[inline(always)]
+    fn export_lyrics(&self, args: &str) {
+        let (format, path) = args.split_once(' ').unwrap_or((args, ""));
+        
+        match LyricsFormat::parse(format) {
+            Some(format) => {
+                let count = format.export_path(path.as_ref(), None, &self.lyrics_languages, false);
+                
+                status!("Exported lyrics of {} songs", count);
+            }
+            None => error!("Unknown lyrics format {}, expected lrc, srt or txt", format),
+        }
+    }
+    
+    // Starts recording the timing of the unsynced lyrics of the current song, or stops and saves it
+    fn toggle_lyrics_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            if recorder.is_empty() {
+                show_notification("Lyrics recording stopped");
+            }
+            else {
+                match recorder.save(self.record_lyrics_to) {
+                    Ok(()) => show_notification("Lyrics timing saved"),
+                    Err(e) => {
+                        // kept so that it can be saved again, e.g. after moving away the .lrc file in the way
+                        error!(e, "Failed to save lyrics timing of {}", recorder.get_song());
+                        show_notification("Failed to save lyrics timing, still recording");
+                        self.recorder.replace(recorder);
+                        return;
+                    }
+                }
+            }
+            
+            self.show_lyrics(recorder.get_song());
+            self.update_lyrics(self.player.get_position());
+        }
+        else if let Some(song) = self.playlist.get_history().get_current().cloned() {
+            if let PlayerState::Play | PlayerState::Pause = self.player.get_state() {
+                match Song::unsynced_lyrics(&song, &self.preferred_languages()) {
+                    Ok(Some((lang, text))) => if let Some(recorder) = LyricsRecorder::new(song.clone(), lang, &text) {
+                        self.recorder.replace(recorder);
+                        self.lyrics_offset = 0;
+                        self.show_recording();
+                        
+                        show_notification("Recording lyrics timing");
+                    }
+                    Ok(None) => show_notification("The song has no lyrics to sync"),
+                    Err(e) => error!(e, "Failed to read tag from {}", song),
+                }
+            }
+        }
+    }
+    
+    #[inline(always)]
+    fn stamp_lyrics_line(&mut self) {
+        let time = self.player.get_position();
+        
+        if let Some(recorder) = &mut self.recorder {
+            if recorder.stamp(time) {
+                if recorder.is_done() {
+                    show_notification("Every line is synced, stop recording to save");
+                }
+                
+                self.show_recording();
+            }
+        }
+    }
+    
+    #[inline(always)]
+    fn undo_lyrics_stamp(&mut self) {
+        if self.recorder.as_mut().and_then(LyricsRecorder::undo).is_some() {
+            self.show_recording();
+        }
+    }
+    
+    #[inline(always)]
+    fn discard_lyrics_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            show_notification("Lyrics recording discarded");
+            
+            self.show_lyrics(recorder.get_song());
+        }
+    }
+    
+    // The floating lyrics show the last stamped line and the next line to stamp
+    #[inline(always)]
+    fn show_recording(&mut self) {
+        if let Some(recorder) = &self.recorder {
+            self.lyrics_lines.set(recorder.preview());
+            self.lyrics_estimated = false;
+            self.unsynced_lyrics = None;
+            
+            if let Some(lyrics) = &mut self.lyrics {
+                if let Err(e) = lyrics.set_lyrics(recorder.preview()) {
+                    error!(e, "Failed to display lyrics")
+                }
             }
+            
+            self.push_lyrics();
+        }
+        
+        self.update_lyrics(self.player.get_position());
+    }
+    
+    // The preferred languages with the one chosen for this session first
+    #[inline(always)]
+    fn preferred_languages(&self) -> Vec<String> {
+        self.lyrics_language.iter()
+            .chain(self.lyrics_languages.iter().filter(|l| !self.lyrics_language.as_ref().is_some_and(|lang| l.eq_ignore_ascii_case(lang))))
+            .cloned()
+            .collect()
+    }
+    
+    // Tries the language before the preferred ones for this session and shows the lyrics of the current song again
+    #[inline(always)]
+    fn set_lyrics_language(&mut self, lang: String) {
+        self.lyrics_language.replace(lang);
+        
+        if let Some(song) = self.playlist.get_history().get_current().cloned() {
+            if let PlayerState::Play | PlayerState::Pause = self.player.get_state() {
+                self.show_lyrics(&song);
+                self.update_lyrics(self.player.get_position());
+            }
+        }
+    }
+    
+    #[inline(always)]
+    fn next_lyrics_language(&mut self) {
+        if let Some(song) = self.playlist.get_history().get_current().cloned() {
+            match Song::synced_lyrics_languages(&song) {
+                Ok(languages) if !languages.is_empty() => {
+                    let current = self.lyrics_language.as_ref().or(self.lyrics_languages.first()).and_then(|lang| languages.iter().position(|l| l.eq_ignore_ascii_case(lang)));
+                    let lang = languages[current.map_or(0, |i| (i + 1) % languages.len())].clone();
+                    
+                    show_notification(format!("Lyrics language: {}", lang));
+                    self.set_lyrics_language(lang);
+                }
+                Ok(_) => {}
+                Err(e) => error!(e, "Failed to read tag from {}", song),
+            }
+        }
+    }
+    
+    // Replaces the lyrics with the ones of the backend chosen in settings
+    #[inline(always)]
+    fn start_lyrics(&mut self) {
+        self.lyrics.take();
+        
+        match Lyrics::with_backend(self.lyrics_backend, self.lyrics_layout.clone()) {
+            Ok(lyrics) => { self.lyrics.replace(lyrics); }
+            Err(e) => error!(e, "Failed to initialize lyrics")
         }
     }
     
     #[inline(always)]
     fn clear_lyrics(&mut self) {
+        self.lyrics_lines = Lines::new();
+        self.lyrics_estimated = false;
+        self.unsynced_lyrics = None;
+        self.push_lyrics();
+        
         if let Some(lyrics) = &mut self.lyrics {
             if let Err(e) = lyrics.clear() {
                 error!(e, "Failed to clear lyrics")
@@ -651,6 +1331,84 @@ impl App {
 }
 
 
+const ARG_EXPORT_LOG: &str = "--export-log=";
+const ARG_FROM: &str = "--from=";
+const ARG_TO: &str = "--to=";
+const ARG_OUTPUT: &str = "--output=";
+const ARG_EXPORT_LYRICS: &str = "--export-lyrics=";
+const ARG_INPUT: &str = "--input=";
+const ARG_FORCE: &str = "--force";
+
+
+// music-player --export-log=csv|json [--from=YYYY-MM-DD] [--to=YYYY-MM-DD] [--output=PATH]
+fn export_log(args: &[String]) -> bool {
+    let get = |prefix: &str| args.iter().find_map(|arg| arg.strip_prefix(prefix));
+    
+    let Some(name) = get(ARG_EXPORT_LOG) else { return false };
+    let Some(format) = ExportFormat::parse(name) else {
+        error!("Unknown export format {}, expected csv or json", name);
+        return true;
+    };
+    
+    // windows builds without the console feature have no standard output to print to
+    let output = get(ARG_OUTPUT).map(str::to_string)
+        .or_else(|| cfg!(all(target_os = "windows", not(feature = "console"))).then(|| format!("listening.{}", name)));
+    
+    let from = match get(ARG_FROM).map(|s| parse_date(s).ok_or(s)).transpose() {
+        Ok(from) => from.unwrap_or(0),
+        Err(s) => { error!("Invalid date {}, expected YYYY-MM-DD", s); return true; }
+    };
+    
+    let to = match get(ARG_TO).map(|s| end_of_date(s).ok_or(s)).transpose() {
+        Ok(to) => to.unwrap_or(u64::MAX),
+        Err(s) => { error!("Invalid date {}, expected YYYY-MM-DD", s); return true; }
+    };
+    
+    match ListeningLog::new(App::cache_path().join("listening.log")).read(from, to) {
+        Ok(entries) => {
+            let s = format.export(&entries);
+            
+            match output {
+                Some(path) => if let Err(e) = write(&path, s) { error!(e, "Failed to write {}", path) }
+                None => print!("{}", s),
+            }
+        }
+        Err(e) => error!(e, "Failed to read listening log"),
+    }
+    
+    true
+}
+
+
+// music-player --export-lyrics=lrc|srt|txt --input=PATH [--output=PATH]
+// the input can be a song or a directory of songs, the output is a file or a directory respectively
+fn export_lyrics(args: &[String]) -> bool {
+    let get = |prefix: &str| args.iter().find_map(|arg| arg.strip_prefix(prefix));
+    
+    let Some(format) = get(ARG_EXPORT_LYRICS) else { return false };
+    let Some(format) = LyricsFormat::parse(format) else {
+        error!("Unknown lyrics format {}, expected lrc, srt or txt", format);
+        return true;
+    };
+    
+    let Some(input) = get(ARG_INPUT) else {
+        error!("Missing {}PATH of a song or a directory", ARG_INPUT);
+        return true;
+    };
+    
+    let languages = Settings::load(&App::config_path().join("settings.json")).unwrap_or_default().lyrics_languages;
+    let count = format.export_path(input.as_ref(), get(ARG_OUTPUT).map(AsRef::as_ref), &languages, args.iter().any(|arg| arg == ARG_FORCE));
+    
+    status!("Exported lyrics of {} songs", count);
+    
+    true
+}
+
+
 fn main() {
-    App::run();
+    let args: Vec<_> = args().collect();
+    
+    if !export_log(&args) && !export_lyrics(&args) {
+        App::run();
+    }
 }
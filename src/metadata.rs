@@ -1,8 +1,17 @@
-use std::{error::Error, path::Path, time::Duration};
+use std::{error::Error, fmt::Display, path::{Path, PathBuf}, time::Duration};
 
-use id3::{Tag, TagLike};
+use id3::{frame::{SynchronisedLyrics, TimestampFormat}, Content, Frame, Tag, TagLike};
 use serde_derive::{Deserialize, Serialize};
 
+use crate::{error, lrc::Lrc, lyrics::Line};
+
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LyricsFrame {
+    lang: String,
+    description: String,
+    text: String,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Song {
@@ -10,32 +19,239 @@ pub struct Song {
     title: Option<String>,
     album: Option<String>,
     artists: Vec<String>,
-    lyrics: Option<String>,
+    lyrics: Option<String>, // in the most preferred language
+    lyrics_frames: Vec<LyricsFrame>,
+    synced_lyrics_frames: Vec<LyricsFrame>,
+}
+
+// Where the synced lyrics shown were read from
+pub enum LyricsSource {
+    Sylt { lang: String, description: String },
+    Lrc(PathBuf),
+}
+
+pub enum LyricsWriteError {
+    Io(std::io::Error),
+    Tag(id3::Error),
+    NotFound,
+    MpegFrames,
+    Exists(PathBuf),
+}
+
+// The first language in the list that has a frame, or the first frame if none of them has
+#[inline(always)]
+fn preferred<'a, T>(frames: impl Iterator<Item = &'a T>, lang: impl Fn(&T) -> &str, languages: &[String]) -> Option<&'a T> {
+    let frames: Vec<_> = frames.collect();
+    
+    languages.iter()
+        .find_map(|l| frames.iter().find(|frame| lang(frame).eq_ignore_ascii_case(l)))
+        .or(frames.first())
+        .copied()
+}
+
+#[inline(always)]
+fn sylt_lines(l: &SynchronisedLyrics) -> Vec<(Duration, String)> {
+    let mut v: Vec<_> = l.content.iter().map(|(t, s)| (Duration::from_millis((*t).into()), s.clone())).collect();
+    
+    v.sort_by_key(|&(d, _)| d);
+    
+    v
+}
+
+#[inline(always)]
+fn read_lrc(path: &Path) -> Option<Vec<(Duration, String)>> {
+    match Lrc::read(path) {
+        Ok(lrc) => Some(lrc.synced_lyrics()).filter(|lines| !lines.is_empty()),
+        Err(e) => { error!(e, "Failed to read lyrics from {}", path.display()); None }
+    }
 }
 
 impl Song {
     #[inline(always)]
-    pub fn new(path: impl AsRef<Path> + Into<String>) -> Result<Self, impl Error> {
+    pub fn new(path: impl AsRef<Path> + Into<String>, languages: &[String]) -> Result<Self, impl Error> {
         Tag::read_from_path(path.as_ref()).map(|tag| Song {
             path: path.into(),
             title: tag.title().map(str::to_string),
             album: tag.album().map(str::to_string),
             artists: tag.artists().unwrap_or_default().into_iter().map(str::to_string).collect(),
-            lyrics: tag.lyrics().find(|lyrics| lyrics.lang == "eng").map(|lyrics| &lyrics.text).filter(|s| !s.is_empty()).map(Clone::clone),
+            lyrics: preferred(tag.lyrics().filter(|lyrics| !lyrics.text.is_empty()), |lyrics| &lyrics.lang, languages).map(|lyrics| lyrics.text.clone()),
+            lyrics_frames: tag.lyrics().map(|lyrics| LyricsFrame {
+                lang: lyrics.lang.clone(),
+                description: lyrics.description.clone(),
+                text: lyrics.text.clone(),
+            }).collect(),
+            synced_lyrics_frames: tag.synchronised_lyrics().map(|lyrics| LyricsFrame {
+                lang: lyrics.lang.clone(),
+                description: lyrics.description.clone(),
+                text: Line::group(sylt_lines(lyrics)).into_iter().map(|line| line.text).collect::<Vec<_>>().join("\n"),
+            }).collect(),
+        })
+    }
+    
+    // The language and text of the unsynced lyrics in the most preferred language
+    #[inline(always)]
+    pub fn unsynced_lyrics(path: impl AsRef<Path>, languages: &[String]) -> Result<Option<(String, String)>, impl Error> {
+        Tag::read_from_path(path).map(|tag| {
+            preferred(tag.lyrics().filter(|lyrics| !lyrics.text.is_empty()), |lyrics| &lyrics.lang, languages).map(|lyrics| (lyrics.lang.clone(), lyrics.text.clone()))
         })
     }
     
     #[inline(always)]
-    pub fn synced_lyrics(path: impl AsRef<Path>) -> Result<Option<Vec<(Duration, String)>>, impl Error> {
-        Tag::read_from_path(path).map(|tag|
-            tag.synchronised_lyrics()
-                .find(|l| l.lang == "eng")
-                .map(|l| {
-                    let mut v: Vec<_> = l.content.iter().map(|(t, s)| (Duration::from_millis((*t).into()), s.clone())).collect();
-                    
-                    v.sort_by_key(|&(d, _)| d);
-                    
-                    v
-                }))
+    pub fn synced_lyrics(path: impl AsRef<Path>, languages: &[String]) -> Result<Option<Vec<(Duration, String)>>, impl Error> {
+        Tag::read_from_path(path).map(|tag| preferred(tag.synchronised_lyrics(), |l| &l.lang, languages).map(sylt_lines))
+    }
+    
+    // Languages of the SYLT frames in the order they appear in the tag, without duplicates
+    #[inline(always)]
+    pub fn synced_lyrics_languages(path: impl AsRef<Path>) -> Result<Vec<String>, impl Error> {
+        Tag::read_from_path(path).map(|tag| {
+            let mut languages: Vec<String> = Vec::new();
+            
+            for l in tag.synchronised_lyrics() {
+                if !languages.iter().any(|lang| lang.eq_ignore_ascii_case(&l.lang)) {
+                    languages.push(l.lang.clone());
+                }
+            }
+            
+            languages
+        })
+    }
+    
+    // Embedded SYLT lyrics win over a .lrc sidecar unless prefer_lrc is set
+    pub fn load_synced_lyrics(path: &str, lyrics_dir: Option<&Path>, prefer_lrc: bool, languages: &[String]) -> Option<(LyricsSource, Vec<(Duration, String)>)> {
+        let sylt = || match Tag::read_from_path(path) {
+            Ok(tag) => preferred(tag.synchronised_lyrics(), |l| &l.lang, languages).map(|l| (
+                LyricsSource::Sylt { lang: l.lang.clone(), description: l.description.clone() },
+                sylt_lines(l),
+            )),
+            Err(e) => { error!(e, "Failed to read tag from {}", path); None }
+        };
+        
+        let lrc = || Lrc::find(path, lyrics_dir, languages).and_then(|lrc| read_lrc(&lrc).map(|lines| (LyricsSource::Lrc(lrc), lines)));
+        
+        if prefer_lrc {
+            lrc().or_else(sylt)
+        }
+        else {
+            sylt().or_else(lrc)
+        }
+    }
+    
+    // A translation has to be exactly in the language, from a SYLT frame or <basename>.<lang>.lrc
+    pub fn load_translation(path: &str, lyrics_dir: Option<&Path>, prefer_lrc: bool, lang: &str) -> Option<Vec<(Duration, String)>> {
+        let sylt = || match Tag::read_from_path(path) {
+            Ok(tag) => tag.synchronised_lyrics().find(|l| l.lang.eq_ignore_ascii_case(lang)).map(sylt_lines),
+            Err(e) => { error!(e, "Failed to read tag from {}", path); None }
+        };
+        
+        let lrc = || Lrc::find_translation(path, lyrics_dir, lang).and_then(|lrc| read_lrc(&lrc));
+        
+        if prefer_lrc {
+            lrc().or_else(sylt)
+        }
+        else {
+            sylt().or_else(lrc)
+        }
+    }
+}
+
+
+impl LyricsSource {
+    // Makes an offset permanent, positive shows the lyrics earlier
+    pub fn write_offset(&self, song: &str, offset: i64) -> Result<(), LyricsWriteError> {
+        match self {
+            LyricsSource::Lrc(path) => Lrc::add_offset(path, offset).map_err(LyricsWriteError::Io),
+            LyricsSource::Sylt { lang, description } => {
+                let mut tag = Tag::read_from_path(song).map_err(LyricsWriteError::Tag)?;
+                
+                add_sylt_offset(&mut tag, lang, description, offset)?;
+                tag.write_to_path(song, tag.version()).map_err(LyricsWriteError::Tag)
+            }
+        }
+    }
+}
+
+
+// The offset is in milliseconds, the length of an MPEG frame depends on the song so those are left as they are
+fn add_sylt_offset(tag: &mut Tag, lang: &str, description: &str, offset: i64) -> Result<(), LyricsWriteError> {
+    let mut lyrics = tag.synchronised_lyrics()
+        .find(|l| l.lang == lang && l.description == description)
+        .cloned()
+        .ok_or(LyricsWriteError::NotFound)?;
+    
+    if lyrics.timestamp_format != TimestampFormat::Ms {
+        return Err(LyricsWriteError::MpegFrames);
+    }
+    
+    for (time, _) in &mut lyrics.content {
+        *time = (*time as i64 - offset).clamp(0, u32::MAX as i64) as u32;
+    }
+    
+    tag.add_frame(Frame::with_content("SYLT", Content::SynchronisedLyrics(lyrics)));
+    
+    Ok(())
+}
+
+
+impl Display for LyricsWriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LyricsWriteError::Io(e) => write!(f, "Failed to write lyrics: {}", e),
+            LyricsWriteError::Tag(e) => write!(f, "Failed to write tag: {}", e),
+            LyricsWriteError::NotFound => write!(f, "The lyrics are no longer in the tag"),
+            LyricsWriteError::MpegFrames => write!(f, "The lyrics are timed in MPEG frames, which cannot be offset"),
+            LyricsWriteError::Exists(path) => write!(f, "{} already exists", path.display()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    
+    use id3::{frame::SynchronisedLyricsType, Version};
+    
+    use super::*;
+    
+    fn tag(timestamp_format: TimestampFormat) -> Tag {
+        let mut tag = Tag::new();
+        
+        tag.add_frame(SynchronisedLyrics {
+            lang: "eng".to_string(),
+            timestamp_format,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: String::new(),
+            content: vec![(100, "a".to_string()), (1500, "b".to_string())],
+        });
+        
+        tag
+    }
+    
+    #[test]
+    fn sylt_offset_round_trip() {
+        let mut tag = tag(TimestampFormat::Ms);
+        
+        assert!(add_sylt_offset(&mut tag, "eng", "", 200).is_ok());
+        
+        let mut bytes = Vec::new();
+        
+        tag.write_to(&mut bytes, Version::Id3v24).expect("Failed to write tag");
+        
+        let tag = Tag::read_from2(Cursor::new(bytes)).expect("Failed to read tag");
+        let lyrics: Vec<_> = tag.synchronised_lyrics().collect();
+        
+        assert_eq!(lyrics.len(), 1);
+        assert_eq!(lyrics[0].timestamp_format, TimestampFormat::Ms);
+        assert_eq!(lyrics[0].content, [(0, "a".to_string()), (1300, "b".to_string())]);
+    }
+    
+    #[test]
+    fn sylt_offset_in_mpeg_frames() {
+        let mut tag = tag(TimestampFormat::Mpeg);
+        
+        assert!(matches!(add_sylt_offset(&mut tag, "eng", "", 200), Err(LyricsWriteError::MpegFrames)));
+        assert_eq!(tag.synchronised_lyrics().next().map(|l| l.content[1].0), Some(1500));
+        assert!(matches!(add_sylt_offset(&mut tag, "deu", "", 200), Err(LyricsWriteError::NotFound)));
     }
 }
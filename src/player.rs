@@ -1,6 +1,5 @@
 use std::time::Duration;
 
-use id3::{frame::SynchronisedLyricsType, v1v2::read_from_path};
 use playback_rs::Song;
 use serde::{Deserialize, Serialize};
 
@@ -18,7 +17,6 @@ pub enum PlayerState {
 pub struct Player {
     player: playback_rs::Player,
     state: PlayerState,
-    lyrics: Vec<(u32, String)>,
     pub mute: bool,
     pub volume: f32,
 }
@@ -37,7 +35,6 @@ impl Player {
         Self {
             player: playback_rs::Player::new(None).expect("Failed to initialize player"),
             state: PlayerState::Idle,
-            lyrics: Vec::new(),
             mute: false,
             volume: 1.,
         }
@@ -68,11 +65,14 @@ impl Player {
     }
     
     #[inline(always)]
-    pub fn play(&mut self, path: &str) {
+    // Whether the song is now playing, the state is left as it was otherwise
+    pub fn play(&mut self, path: &str) -> bool {
         match Song::from_file(path, None) {
             Ok(song) => {
                 if let Some(e) = self.player.play_song_now(&song, None).err() {
-                    error!(e, "Failed to play song")
+                    error!(e, "Failed to play song");
+                    
+                    false
                 }
                 else {
                     task!("Playing song {}", path);
@@ -80,18 +80,10 @@ impl Player {
                     self.player.set_playing(true);
                     self.state = PlayerState::Play;
                     
-                    match read_from_path(path) {
-                        Ok(tag) => {
-                            if let Some(lyrics) = tag.synchronised_lyrics().find(|sl| sl.lang == "eng" && sl.content_type == SynchronisedLyricsType::Lyrics) {
-                                self.lyrics = lyrics.content.clone();
-                                self.lyrics.sort_by_key(|&(time, _)| time);
-                            }
-                        }
-                        Err(e) => error!(e, "Cannot read tag from {}", path)
-                    }
+                    true
                 }
             }
-            Err(e) => error!(e, "Failed to load song {}", path),
+            Err(e) => { error!(e, "Failed to load song {}", path); false }
         }
     }
     
@@ -1,4 +1,6 @@
 use bitvec::vec::BitVec;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
 use serde_derive::{Serialize, Deserialize};
 use serde_json::{to_value, Value};
 
@@ -37,7 +39,7 @@ impl RepeatMode {
 }
 
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Playlist {
     songs: Vec<String>,
     history: History<String>,
@@ -46,6 +48,26 @@ pub struct Playlist {
     play_index_deleted: bool,
     repeat_mode: RepeatMode,
     pub shuffle: bool,
+    // unplayed songs (except the current one) in the order they will be shuffled
+    #[serde(default)]
+    shuffle_order: Vec<usize>,
+    #[serde(default = "ChaCha12Rng::from_entropy")]
+    rng: ChaCha12Rng,
+    // the frontend playlist the songs come from
+    #[serde(default)]
+    pub id: String,
+}
+
+
+// Everything restored by undoing or redoing a playlist edit
+#[derive(Clone)]
+pub struct PlaylistSnapshot {
+    id: String,
+    songs: Vec<String>,
+    played: BitVec,
+    play_index: usize,
+    play_index_deleted: bool,
+    shuffle_order: Vec<usize>,
 }
 
 
@@ -60,14 +82,50 @@ impl Playlist {
             play_index_deleted: false,
             repeat_mode: RepeatMode::NoRepeat,
             shuffle: false,
+            shuffle_order: Vec::new(),
+            rng: ChaCha12Rng::from_entropy(),
+            id: String::new(),
         }
     }
     
     #[inline(always)]
-    pub unsafe fn history_keep_at_most(&mut self, capacity: usize) {
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+        self.reshuffle();
+    }
+    
+    #[inline(always)]
+    pub fn history_keep_at_most(&mut self, capacity: usize) {
         self.history.set_capacity(capacity)
     }
     
+    #[inline(always)]
+    pub fn snapshot(&self) -> PlaylistSnapshot {
+        PlaylistSnapshot {
+            id: self.id.clone(),
+            songs: self.songs.clone(),
+            played: self.played.clone(),
+            play_index: self.play_index,
+            play_index_deleted: self.play_index_deleted,
+            shuffle_order: self.shuffle_order.clone(),
+        }
+    }
+    
+    #[inline(always)]
+    pub fn restore(&mut self, snapshot: PlaylistSnapshot) {
+        self.id = snapshot.id;
+        self.songs = snapshot.songs;
+        self.played = snapshot.played;
+        self.play_index = snapshot.play_index;
+        self.play_index_deleted = snapshot.play_index_deleted;
+        self.shuffle_order = snapshot.shuffle_order;
+    }
+    
+    #[inline(always)]
+    pub fn get_songs(&self) -> &[String] {
+        &self.songs
+    }
+    
     #[inline(always)]
     pub fn count(&self) -> usize {
         self.songs.len()
@@ -85,6 +143,10 @@ impl Playlist {
         
         self.songs.push(song);
         self.played.push(false);
+        
+        if self.play_index_deleted || self.play_index != self.count() - 1 {
+            self.insert_shuffled(self.count() - 1);
+        }
     }
     
     #[inline(always)]
@@ -92,6 +154,9 @@ impl Playlist {
         self.songs.remove(index);
         self.played.remove(index);
         
+        self.shuffle_order.retain(|&i| i != index);
+        self.shuffle_order.iter_mut().filter(|i| **i > index).for_each(|i| *i -= 1);
+        
         if index < self.play_index {
             self.play_index -= 1;
         }
@@ -104,6 +169,7 @@ impl Playlist {
     pub fn clear(&mut self) {
         self.songs.clear();
         self.played.clear();
+        self.shuffle_order.clear();
         self.play_index_deleted = true;
     }
     
@@ -120,6 +186,21 @@ impl Playlist {
         let played = self.played.remove(from);
         self.played.insert(to, played);
         
+        for i in &mut self.shuffle_order {
+            if *i == from {
+                *i = to;
+            }
+            else {
+                if *i > from {
+                    *i -= 1;
+                }
+                
+                if *i >= to {
+                    *i += 1;
+                }
+            }
+        }
+        
         if from == self.play_index {
             self.play_index = to;
         }
@@ -151,6 +232,15 @@ impl Playlist {
     
     #[inline(always)]
     pub fn select(&mut self, index: usize) -> &str {
+        let previous = self.play_index;
+        
+        self.shuffle_order.retain(|&i| i != index);
+        
+        // the previous song was skipped before it finished, so it has not been played yet
+        if !self.play_index_deleted && previous != index && previous < self.count() && !self.played[previous] && !self.shuffle_order.contains(&previous) {
+            self.insert_shuffled(previous);
+        }
+        
         self.play_index_deleted = false;
         self.play_index = index;
         self.history.push(self.songs[index].clone());
@@ -163,6 +253,40 @@ impl Playlist {
         self.history.look_back().map(Clone::clone)
     }
     
+    #[inline(always)]
+    pub fn look_forward(&mut self) -> Option<String> {
+        self.history.advance().cloned()
+    }
+    
+    #[inline(always)]
+    pub fn history_jump(&mut self, index: usize) -> Option<String> {
+        self.history.jump(index).cloned()
+    }
+    
+    #[inline(always)]
+    fn insert_shuffled(&mut self, index: usize) {
+        let i = self.rng.gen_range(0..=self.shuffle_order.len());
+        
+        self.shuffle_order.insert(i, index);
+    }
+    
+    fn reshuffle(&mut self) {
+        let current = (!self.play_index_deleted).then_some(self.play_index);
+        
+        self.shuffle_order.clear();
+        
+        for i in self.played.iter_zeros().filter(|&i| Some(i) != current).collect::<Vec<_>>() {
+            self.insert_shuffled(i);
+        }
+    }
+    
+    // songs that will be played next under the current repeat mode and shuffle state
+    pub fn upcoming(&self, n: usize) -> Vec<String> {
+        let mut playlist = self.clone();
+        
+        (0..n).map_while(|_| playlist.poll().map(str::to_string)).collect()
+    }
+    
     pub fn poll(&mut self) -> Option<&str> {
         let repeat = match self.repeat_mode {
             RepeatMode::NoRepeat => false,
@@ -184,17 +308,27 @@ impl Playlist {
             
             if self.count() > 0 {
                 if self.shuffle {
-                    let n = self.played.count_ones();
+                    if self.shuffle_order.is_empty() {
+                        self.reshuffle();
+                    }
                     
-                    if n < self.count() {
-                        Some(self.select(self.played.iter_zeros().nth(rand::random::<usize>() % (self.count() - n)).unwrap()))
+                    if !self.shuffle_order.is_empty() {
+                        Some(self.select(self.shuffle_order[0]))
                     }
                     else if repeat { // all songs played once
                         self.played.fill(false);
-                        Some(self.select(rand::random::<usize>() % self.count()))
+                        self.reshuffle();
+                        
+                        if self.shuffle_order.is_empty() { // the only song in playlist
+                            Some(self.select(self.play_index))
+                        }
+                        else {
+                            Some(self.select(self.shuffle_order[0]))
+                        }
                     }
                     else {
                         self.played.fill(false);
+                        self.reshuffle();
                         None
                     }
                 }
@@ -203,10 +337,12 @@ impl Playlist {
                 }
                 else if repeat { // playlist finished
                     self.played.fill(false);
+                    self.reshuffle();
                     Some(self.select(0))
                 }
                 else {
                     self.played.fill(false);
+                    self.reshuffle();
                     None
                 }
             }
@@ -220,3 +356,51 @@ impl Playlist {
     }
 }
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::undo::UndoStack;
+    
+    fn playlist(id: &str, songs: &[&str]) -> Playlist {
+        let mut playlist = Playlist::new(10);
+        
+        playlist.id = id.to_string();
+        
+        for song in songs {
+            playlist.append(song.to_string());
+        }
+        
+        playlist
+    }
+    
+    #[test]
+    fn undo_playlist_switch() {
+        let mut playlist = playlist("a", &["1", "2"]);
+        let mut edits = UndoStack::new(10);
+        
+        // the commands sent by the frontend when another playlist starts playing
+        edits.begin_group();
+        edits.record(|| playlist.snapshot());
+        playlist.id = "b".to_string();
+        edits.record(|| playlist.snapshot());
+        playlist.clear();
+        edits.record(|| playlist.snapshot());
+        playlist.append("3".to_string());
+        edits.end_group();
+        
+        let snapshot = edits.undo(|| playlist.snapshot()).expect("Nothing to undo");
+        
+        playlist.restore(snapshot);
+        assert_eq!(playlist.id, "a");
+        assert_eq!(playlist.get_songs(), ["1", "2"]);
+        assert!(edits.undo(|| playlist.snapshot()).is_none());
+        
+        let snapshot = edits.redo(|| playlist.snapshot()).expect("Nothing to redo");
+        
+        playlist.restore(snapshot);
+        assert_eq!(playlist.id, "b");
+        assert_eq!(playlist.get_songs(), ["3"]);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{fs::OpenOptions, io::{ErrorKind, Write}, path::Path, time::Duration};
+
+use id3::{frame::{SynchronisedLyrics, SynchronisedLyricsType, TimestampFormat}, Content, Frame, Tag, TagLike};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{lrc::Lrc, metadata::LyricsWriteError};
+
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    Sylt,
+    Lrc,
+}
+
+
+// Syncs unsynced lyrics by stamping the time each line starts, one line after another
+pub struct LyricsRecorder {
+    song: String,
+    lang: String,
+    lines: Vec<String>,
+    stamps: Vec<Duration>,
+}
+
+
+impl LyricsRecorder {
+    // Empty lines are skipped, returns None if there is nothing to sync
+    #[inline(always)]
+    pub fn new(song: String, lang: String, text: &str) -> Option<Self> {
+        let lines: Vec<String> = text.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect();
+        
+        (!lines.is_empty()).then(|| Self {
+            song,
+            lang,
+            lines,
+            stamps: Vec::new(),
+        })
+    }
+    
+    #[inline(always)]
+    pub fn get_song(&self) -> &str {
+        &self.song
+    }
+    
+    #[inline(always)]
+    pub fn is_done(&self) -> bool {
+        self.stamps.len() == self.lines.len()
+    }
+    
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.stamps.is_empty()
+    }
+    
+    // A line never starts before the line above it, e.g. after rewinding
+    #[inline(always)]
+    pub fn stamp(&mut self, time: Duration) -> bool {
+        if self.is_done() {
+            return false;
+        }
+        
+        self.stamps.push(self.stamps.last().map_or(time, |&last| last.max(time)));
+        
+        true
+    }
+    
+    #[inline(always)]
+    pub fn undo(&mut self) -> Option<Duration> {
+        self.stamps.pop()
+    }
+    
+    // The lines stamped so far in the same form as SYLT lines
+    #[inline(always)]
+    pub fn synced(&self) -> Vec<(Duration, String)> {
+        self.stamps.iter().zip(&self.lines).map(|(&time, line)| (time, format!("\n{}", line))).collect()
+    }
+    
+    // The lines not stamped yet never become the current line, so the next one to stamp is always shown next
+    #[inline(always)]
+    pub fn preview(&self) -> Vec<(Duration, String)> {
+        let mut lyrics = self.synced();
+        
+        lyrics.extend(self.lines[self.stamps.len()..].iter().map(|line| (Duration::MAX, format!("\n{}", line))));
+        
+        lyrics
+    }
+    
+    // Replaces the SYLT frame in the same language as the unsynced lyrics
+    pub fn save_sylt(&self) -> Result<(), LyricsWriteError> {
+        let mut tag = Tag::read_from_path(&self.song).map_err(LyricsWriteError::Tag)?;
+        
+        tag.add_frame(Frame::with_content("SYLT", Content::SynchronisedLyrics(SynchronisedLyrics {
+            lang: self.lang.clone(),
+            timestamp_format: TimestampFormat::Ms,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: String::new(),
+            content: self.synced().into_iter().map(|(time, line)| (time.as_millis().min(u32::MAX as u128) as u32, line)).collect(),
+        })));
+        
+        tag.write_to_path(&self.song, tag.version()).map_err(LyricsWriteError::Tag)
+    }
+    
+    #[inline(always)]
+    pub fn save(&self, format: RecordFormat) -> Result<(), LyricsWriteError> {
+        match format {
+            RecordFormat::Sylt => self.save_sylt(),
+            RecordFormat::Lrc => self.save_lrc(),
+        }
+    }
+    
+    // Writes <basename>.lrc next to the song, an existing one is never replaced
+    #[inline(always)]
+    pub fn save_lrc(&self) -> Result<(), LyricsWriteError> {
+        let lrc = Lrc {
+            lines: self.stamps.iter().copied().zip(self.lines.iter().cloned()).collect(),
+            ..Default::default()
+        };
+        let path = Path::new(&self.song).with_extension("lrc");
+        
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => file.write_all(lrc.to_string().as_bytes()).map_err(LyricsWriteError::Io),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(LyricsWriteError::Exists(path)),
+            Err(e) => Err(LyricsWriteError::Io(e)),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::{fmt::Display, fs::{read_to_string, write}, io::ErrorKind, path::{Path, PathBuf}, time::Duration};
+
+use serde_derive::{Serialize, Deserialize};
+use serde_json::{from_str, to_string_pretty};
+
+use crate::{history::DEFAULT_HISTORY_CAPACITY, recorder::RecordFormat, LyricsBackend, LyricsLayout, LyricsPosition, LyricsStyle, default_context_lines};
+
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub fps: u16,
+    pub volume_step: f32,
+    #[serde(with = "milliseconds")]
+    pub key_duration: Duration,
+    #[serde(with = "milliseconds")]
+    pub seek_duration: Duration,
+    #[serde(with = "milliseconds")]
+    pub sequence_timeout: Duration,
+    #[serde(with = "milliseconds")]
+    pub lyrics_offset_step: Duration,
+    #[serde(with = "milliseconds")]
+    pub long_press_duration: Duration,
+    #[serde(with = "milliseconds")]
+    pub double_tap_interval: Duration,
+    // multiplies the interval between repeats of seeking every repeat, 1 to keep it steady
+    pub repeat_acceleration: f64,
+    pub history_capacity: usize,
+    pub undo_capacity: usize,
+    // shared directory searched for <basename>.lrc when there is none next to the song
+    pub lyrics_dir: Option<PathBuf>,
+    // ISO 639-2 codes (e.g. eng, jpn, spa) of the lyrics to show first, any other language is used if none of them is found
+    pub lyrics_languages: Vec<String>,
+    // languages of translations shown under the current line, only lyrics exactly in the language are used
+    pub translation_languages: Vec<String>,
+    // use the .lrc sidecar instead of the embedded SYLT lyrics when both exist
+    pub prefer_lrc: bool,
+    // where recorded lyrics timing is saved, "sylt" or "lrc"
+    pub record_lyrics_to: RecordFormat,
+    // shows unsynced lyrics as floating lyrics with an estimated timing when a song has no synced lyrics
+    pub estimate_unsynced_lyrics: bool,
+    // how long each estimated line is shown, 0 to spread the lines evenly over the song
+    #[serde(with = "milliseconds")]
+    pub unsynced_line_duration: Duration,
+    // "auto", "x11", "wayland", "windows", "console" or "none", only the ones compiled in are available
+    pub lyrics_backend: LyricsBackend,
+    // only used when there is no layout saved in the cache
+    pub lyrics_layout: LyricsLayout,
+}
+
+
+pub enum SettingsError {
+    Read(std::io::Error),
+    Write(std::io::Error),
+    Parse(serde_json::Error),
+    Invalid(&'static str),
+}
+
+
+mod milliseconds {
+    use std::time::Duration;
+    
+    use serde::{Deserialize, Deserializer, Serializer};
+    
+    #[inline(always)]
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+    
+    #[inline(always)]
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+
+impl Default for Settings {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            fps: 120,
+            volume_step: 0.05,
+            key_duration: Duration::from_millis(100),
+            seek_duration: Duration::from_secs(5),
+            sequence_timeout: Duration::from_secs(1),
+            lyrics_offset_step: Duration::from_millis(100),
+            long_press_duration: Duration::from_millis(500),
+            double_tap_interval: Duration::from_millis(300),
+            repeat_acceleration: 0.85,
+            history_capacity: DEFAULT_HISTORY_CAPACITY,
+            undo_capacity: 100,
+            lyrics_languages: vec!["eng".to_string()],
+            translation_languages: Vec::new(),
+            lyrics_dir: None,
+            prefer_lrc: false,
+            record_lyrics_to: RecordFormat::Sylt,
+            estimate_unsynced_lyrics: false,
+            unsynced_line_duration: Duration::ZERO,
+            lyrics_backend: LyricsBackend::Auto,
+            lyrics_layout: LyricsLayout {
+                position: LyricsPosition::TopCenter,
+                margin: 48,
+                visible: true,
+                style: LyricsStyle::default(),
+                lines_before: default_context_lines(),
+                lines_after: default_context_lines(),
+                monitor: 0,
+                x: None,
+                y: None,
+            },
+        }
+    }
+}
+
+
+impl Settings {
+    // Missing settings use their default value, a missing file is created with the default settings
+    pub fn load(path: &Path) -> Result<Self, SettingsError> {
+        match read_to_string(path) {
+            Ok(s) => {
+                let settings: Settings = from_str(&s).map_err(SettingsError::Parse)?;
+                
+                if settings.fps == 0 {
+                    Err(SettingsError::Invalid("fps must be greater than 0"))
+                }
+                else if settings.key_duration.is_zero() || settings.seek_duration.is_zero() {
+                    Err(SettingsError::Invalid("key_duration and seek_duration must be greater than 0"))
+                }
+                else if settings.history_capacity == 0 {
+                    Err(SettingsError::Invalid("history_capacity must be greater than 0"))
+                }
+                else if !(0. ..=1.).contains(&settings.volume_step) {
+                    Err(SettingsError::Invalid("volume_step must be between 0 and 1"))
+                }
+                else if let Err(e) = settings.lyrics_layout.style.validate() {
+                    Err(SettingsError::Invalid(e))
+                }
+                else if !(0. ..=1.).contains(&settings.repeat_acceleration) || settings.repeat_acceleration == 0. {
+                    Err(SettingsError::Invalid("repeat_acceleration must be greater than 0 and at most 1"))
+                }
+                else {
+                    Ok(settings)
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let settings = Settings::default();
+                
+                write(path, to_string_pretty(&settings).expect("Failed to serialize")).map_err(SettingsError::Write)?;
+                
+                Ok(settings)
+            }
+            Err(e) => Err(SettingsError::Read(e)),
+        }
+    }
+}
+
+
+impl Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::Read(e) => write!(f, "Failed to read settings: {}", e),
+            SettingsError::Write(e) => write!(f, "Failed to write default settings: {}", e),
+            SettingsError::Parse(e) => write!(f, "Failed to parse settings: {}", e),
+            SettingsError::Invalid(s) => write!(f, "Invalid settings: {}", s),
+        }
+    }
+}
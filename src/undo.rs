@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+
+pub struct UndoStack<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    capacity: usize,
+    groups: usize, // groups can be nested, the outermost one is undone as a whole
+    grouped: bool, // whether the open group has recorded its state yet
+}
+
+
+impl<T> UndoStack<T> {
+    #[inline(always)]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            capacity,
+            groups: 0,
+            grouped: false,
+        }
+    }
+    
+    #[inline(always)]
+    pub fn set_capacity(&mut self, capacity: usize) {
+        if self.undo.len() > capacity {
+            self.undo.drain(..self.undo.len() - capacity);
+        }
+        
+        self.capacity = capacity;
+    }
+    
+    // Every edit until the group is ended is undone together
+    #[inline(always)]
+    pub fn begin_group(&mut self) {
+        if self.groups == 0 {
+            self.grouped = false;
+        }
+        
+        self.groups += 1;
+    }
+    
+    #[inline(always)]
+    pub fn end_group(&mut self) {
+        self.groups = self.groups.saturating_sub(1);
+    }
+    
+    // Ends the groups that will never be ended, e.g. by a frontend that is closed
+    #[inline(always)]
+    pub fn end_all_groups(&mut self) {
+        self.groups = 0;
+    }
+    
+    // Records the state before an edit, discarding anything that could be redone,
+    // only the first edit of a group is recorded
+    #[inline(always)]
+    pub fn record(&mut self, current: impl FnOnce() -> T) {
+        if self.groups > 0 {
+            if self.grouped {
+                return;
+            }
+            
+            self.grouped = true;
+        }
+        
+        self.redo.clear();
+        self.undo.push_back(current());
+        
+        if self.undo.len() > self.capacity {
+            self.undo.pop_front();
+        }
+    }
+    
+    #[inline(always)]
+    pub fn undo(&mut self, current: impl FnOnce() -> T) -> Option<T> {
+        let t = self.undo.pop_back()?;
+        
+        self.redo.push(current());
+        
+        Some(t)
+    }
+    
+    #[inline(always)]
+    pub fn redo(&mut self, current: impl FnOnce() -> T) -> Option<T> {
+        let t = self.redo.pop()?;
+        
+        self.undo.push_back(current());
+        
+        Some(t)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn group_over_several_passes() {
+        let mut edits = UndoStack::new(10);
+        
+        // a group begun by the frontend is kept open by the groups of each pass of commands
+        edits.begin_group();
+        edits.begin_group();
+        edits.record(|| 1);
+        edits.end_group();
+        edits.begin_group();
+        edits.record(|| 2);
+        edits.end_group();
+        edits.end_group();
+        edits.record(|| 3);
+        
+        assert_eq!(edits.undo(|| 4), Some(3));
+        assert_eq!(edits.undo(|| 3), Some(1));
+        assert_eq!(edits.undo(|| 1), None);
+    }
+}